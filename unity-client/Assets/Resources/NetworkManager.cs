@@ -133,13 +133,65 @@ public class NetworkManager : MonoBehaviour
         @for      = p.@for,
         card      = p.card,
         partial   = partial,
-        component = comp
+        component = comp,
+        proof     = resp.data.proof
     }}
         };
         Instance.SendRaw(frame);
         Debug.Log($"[Decrypt] sent decryptCard for {p.@for} (card {p.card})");
     }
 
+    IEnumerator DoSignChallenge(KeyChallengePayload p)
+    {
+        string mySk = KeyPairStore.Instance.GetSecretKey();
+
+        // 1) build JSON body for signChallenge
+        var bodyObj = new
+        {
+            circuit_name = "signChallenge",
+            data = new
+            {
+                g = "3",
+                pk = KeyPairStore.Instance.PublicKey,
+                nonce = p.nonce,
+                sk = mySk
+            }
+        };
+        string bodyJson = JsonConvert.SerializeObject(bodyObj);
+
+        // 2) POST to prover
+        using var www = new UnityEngine.Networking.UnityWebRequest("http://localhost:3000/prove", UnityEngine.Networking.UnityWebRequest.kHttpVerbPOST)
+        {
+            uploadHandler = new UnityEngine.Networking.UploadHandlerRaw(Encoding.UTF8.GetBytes(bodyJson)),
+            downloadHandler = new UnityEngine.Networking.DownloadHandlerBuffer()
+        };
+        www.SetRequestHeader("Content-Type", "application/json");
+        yield return www.SendWebRequest();
+
+        if (www.result != UnityEngine.Networking.UnityWebRequest.Result.Success)
+        {
+            Debug.LogError($"/prove signChallenge failed: {www.error}");
+            yield break;
+        }
+
+        // 3) the proof is the signature, checked by the server against [pk, nonce]
+        var resp = JsonConvert.DeserializeObject<SignChallengeResp>(
+                       www.downloadHandler.text);
+        if (resp == null || !resp.ok || string.IsNullOrEmpty(resp.data?.proof))
+        {
+            Debug.LogError("/prove signChallenge responded ok=false");
+            yield break;
+        }
+
+        var frame = new HubMessage<SignChallengeArg>
+        {
+            target = "signChallenge",
+            arguments = new[] { new SignChallengeArg { signature = resp.data.proof } }
+        };
+        Instance.SendRaw(frame);
+        Debug.Log("[Keys] sent signChallenge");
+    }
+
     private IEnumerator FetchKeysThenEnterGame()
     {
         // 1) Fetch the HTTP keypair
@@ -154,6 +206,8 @@ public class NetworkManager : MonoBehaviour
         };
         // SendRaw is async void, but that's fine here:
         SendRaw(frame);
+        // the server answers with keyChallenge; DoSignChallenge proves we
+        // hold the secret key and only then does the key count
 
         // 3) Now load the GameScene
         SceneManager.LoadScene("GameScene");
@@ -342,6 +396,21 @@ public class NetworkManager : MonoBehaviour
                         SendPickCard(idx);
                     }
 
+                    break;
+                }
+            case "keyChallenge":
+                {
+                    var chalEnv = JsonConvert.DeserializeObject<
+                                     IncomingFrame<KeyChallengePayload>>(json);
+                    var p = chalEnv?.arguments?[0];
+                    if (p == null || string.IsNullOrEmpty(p.nonce))
+                    {
+                        Debug.LogError("keyChallenge frame missing nonce");
+                        break;
+                    }
+
+                    Debug.Log($"[Keys] keyChallenge nonce={p.nonce}");
+                    StartCoroutine(DoSignChallenge(p));
                     break;
                 }
             case "needDecrypt":
@@ -741,6 +810,7 @@ public class NetworkManager : MonoBehaviour
         public class RespData
         {
             public Outputs outputs;
+            public string proof;           // present on /prove responses
             [Serializable]
             public class Outputs
             {
@@ -756,6 +826,7 @@ public class NetworkManager : MonoBehaviour
         public int card;
         public string[] partial;
         public string component;
+        public string proof;       // decryptOneLayer proof, checked by the server
     }
 
     [Serializable]
@@ -766,6 +837,27 @@ public class NetworkManager : MonoBehaviour
         public DecryptCardArg[] arguments;
     }
 
+    [Serializable]
+    public class KeyChallengePayload
+    {
+        public string nonce;     // sign this with our secret key
+    }
+
+    [Serializable]
+    public class SignChallengeResp     // shape of /prove response
+    {
+        public bool ok;
+        public RespData data;
+
+        [Serializable]
+        public class RespData
+        {
+            public string proof;   // the signChallenge proof
+        }
+    }
+
+    [Serializable] class SignChallengeArg { public string signature; }
+
     [Serializable]
     class PartialReadyPayload
     {
@@ -0,0 +1,62 @@
+//! Rebuild a finished game from its exported event log and print where it
+//! ended up.
+//!
+//! Usage: `cargo run --bin replay -- <logs/ROOMID.jsonl>`
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use wss_server::room::{log::EventLog, room::Room};
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: replay <room log .jsonl>");
+        return ExitCode::FAILURE;
+    };
+    match replay(Path::new(&path)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("replay {path}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn replay(path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let log = EventLog::from_json_lines(&text)?;
+    let room_id = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let room = Room::replay(room_id, &log);
+
+    println!(
+        "room {}: {} events, phase {:?}, round {}",
+        room.id,
+        log.entries().len(),
+        room.phase(),
+        room.round()
+    );
+    for (seat, p) in room.seated().enumerate() {
+        let role = p.role.map_or("?".to_string(), |r| format!("{r:?}"));
+        let card = room
+            .taken_cards
+            .get(&p.id)
+            .map_or("-".to_string(), |c| c.to_string());
+        let vote = match room.votes().get(&p.id) {
+            Some(Some(target)) => target.clone(),
+            Some(None) => "abstain".to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "  #{seat} {} ({}) role={role} alive={} card={card} vote={vote}",
+            p.name, p.id, p.is_alive
+        );
+    }
+    match room.winner() {
+        Some(winner) => println!("winner: {winner}"),
+        None => println!("no winner yet"),
+    }
+    Ok(())
+}
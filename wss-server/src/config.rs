@@ -0,0 +1,264 @@
+//! Server settings: built-in defaults, overridden by a TOML file, then by
+//! environment variables and command-line flags.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{config::GameConfig, role::RoleSet},
+    prover::http::HttpBackendConfig,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address the WebSocket listener binds to.
+    pub bind: String,
+    /// Certificate and key to serve `wss://` with.
+    pub tls: Option<TlsConfig>,
+    pub prover: HttpBackendConfig,
+    /// Settings every new room starts with, before the host configures it.
+    pub game: GameConfig,
+    /// An `env_logger` filter: a level such as `info`, optionally followed
+    /// by per-module levels, e.g. `warn,wss_server::room=debug`.
+    pub log_level: String,
+    /// Where finished games' event logs are exported, if anywhere.
+    pub log_dir: Option<PathBuf>,
+    /// Where rooms are saved to survive a restart, if anywhere.
+    pub state_dir: Option<PathBuf>,
+    /// Seconds open connections get to finish when the server stops.
+    pub shutdown_timeout: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain.
+    pub cert: PathBuf,
+    /// PEM private key.
+    pub key: PathBuf,
+    /// Seconds between checks of the files for a renewed certificate;
+    /// unset loads them once.
+    #[serde(default)]
+    pub reload_interval: Option<u64>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:8080".into(),
+            tls: None,
+            prover: HttpBackendConfig::default(),
+            game: GameConfig::default(),
+            log_level: "info".into(),
+            log_dir: None,
+            state_dir: None,
+            shutdown_timeout: 10,
+        }
+    }
+}
+
+/// Command-line flags. Each one can also be given through the environment
+/// variable shown in `--help`; a flag wins over its variable, and both win
+/// over the config file.
+#[derive(Debug, Default, Parser)]
+#[command(name = "wss-server", version, about = "Werewolf game WebSocket server")]
+pub struct Cli {
+    /// TOML file to read settings from.
+    #[arg(long, short, env = "WSS_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address to listen on, e.g. 127.0.0.1:8080.
+    #[arg(long, env = "WSS_BIND")]
+    pub bind: Option<String>,
+    /// PEM certificate chain for TLS.
+    #[arg(long, env = "WSS_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key for TLS.
+    #[arg(long, env = "WSS_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+    /// Base URL of the prover server.
+    #[arg(long, env = "WSS_PROVER_URL")]
+    pub prover_url: Option<String>,
+    /// Seconds to wait for the prover server.
+    #[arg(long, env = "WSS_PROVER_TIMEOUT")]
+    pub prover_timeout: Option<u64>,
+    /// Seats in a new room, dealt the default role composition.
+    #[arg(long, env = "WSS_PLAYERS")]
+    pub players: Option<usize>,
+    /// Seconds a night lasts.
+    #[arg(long, env = "WSS_NIGHT_DURATION")]
+    pub night_duration: Option<u64>,
+    /// Seconds a day lasts.
+    #[arg(long, env = "WSS_DAY_DURATION")]
+    pub day_duration: Option<u64>,
+    /// Seconds a disconnected player has to resume.
+    #[arg(long, env = "WSS_RECONNECT_GRACE")]
+    pub reconnect_grace: Option<u64>,
+    /// Log filter, e.g. `info` or `warn,wss_server::room=debug`.
+    #[arg(long, env = "WSS_LOG_LEVEL")]
+    pub log_level: Option<String>,
+    /// Directory to export finished games' event logs to.
+    #[arg(long, env = "WSS_LOG_DIR")]
+    pub log_dir: Option<PathBuf>,
+    /// Directory to keep room state in for crash recovery.
+    #[arg(long, env = "WSS_STATE_DIR")]
+    pub state_dir: Option<PathBuf>,
+}
+
+impl ServerConfig {
+    /// Settings from this process's flags and environment, and the config
+    /// file they name. Exits with a usage message on a bad flag.
+    pub fn from_args() -> Result<Self, String> {
+        Self::load(Cli::parse())
+    }
+
+    /// Defaults, then the file named by `cli.config`, then `cli` itself.
+    pub fn load(cli: Cli) -> Result<Self, String> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply(cli)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::from_toml(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Parse a config file; missing keys keep their defaults.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    fn apply(&mut self, cli: Cli) -> Result<(), String> {
+        if let Some(bind) = cli.bind {
+            self.bind = bind;
+        }
+        if let (Some(cert), Some(key)) = (cli.tls_cert, cli.tls_key) {
+            let reload_interval = self.tls.as_ref().and_then(|tls| tls.reload_interval);
+            self.tls = Some(TlsConfig {
+                cert,
+                key,
+                reload_interval,
+            });
+        }
+        if let Some(url) = cli.prover_url {
+            self.prover.url = url;
+        }
+        if let Some(secs) = cli.prover_timeout {
+            self.prover.timeout = Duration::from_secs(secs);
+        }
+        if let Some(players) = cli.players {
+            self.game.roles = RoleSet::for_players(players)?;
+        }
+        if let Some(secs) = cli.night_duration {
+            self.game.night_duration = secs;
+        }
+        if let Some(secs) = cli.day_duration {
+            self.game.day_duration = secs;
+        }
+        if let Some(secs) = cli.reconnect_grace {
+            self.game.reconnect_grace = secs;
+        }
+        if let Some(level) = cli.log_level {
+            self.log_level = level;
+        }
+        if cli.log_dir.is_some() {
+            self.log_dir = cli.log_dir;
+        }
+        if cli.state_dir.is_some() {
+            self.state_dir = cli.state_dir;
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.game
+            .validate()
+            .map_err(|e| format!("game settings: {e}"))
+    }
+
+    /// Send the `log` output of the whole process to stderr, filtered by
+    /// `log_level`. Does nothing if a logger is already installed.
+    pub fn init_logging(&self) {
+        let _ = env_logger::Builder::new()
+            .parse_filters(&self.log_level)
+            .try_init();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("wss-server").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn file_settings_keep_defaults_for_missing_keys() {
+        let config = ServerConfig::from_toml(
+            r#"
+            bind = "127.0.0.1:9000"
+
+            [prover]
+            timeout = 5
+
+            [prover.circuits]
+            shuffle = "shuffleDeck{n}"
+
+            [game]
+            night_duration = 10
+            roles = { werewolves = 2, seers = 1, villagers = 5 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.bind, "127.0.0.1:9000");
+        assert_eq!(config.prover.timeout, Duration::from_secs(5));
+        assert_eq!(config.prover.url, HttpBackendConfig::default().url);
+        assert_eq!(config.prover.circuits.shuffle, "shuffleDeck{n}");
+        assert_eq!(config.game.night_duration, 10);
+        assert_eq!(config.game.player_count(), 8);
+        assert_eq!(config.game.day_duration, GameConfig::default().day_duration);
+        assert_eq!(config.log_level, "info");
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(ServerConfig::from_toml("bnid = \"0.0.0.0:1\"").is_err());
+        assert!(ServerConfig::from_toml("[game]\nnight = 10").is_err());
+    }
+
+    #[test]
+    fn flags_override_the_file() {
+        let path = std::env::temp_dir().join(format!("wss-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "bind = \"127.0.0.1:9000\"\nlog_level = \"debug\"\n").unwrap();
+        let config = ServerConfig::load(parse(&[
+            "--config",
+            path.to_str().unwrap(),
+            "--bind",
+            "127.0.0.1:9100",
+            "--players",
+            "6",
+        ]))
+        .unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(config.bind, "127.0.0.1:9100");
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.game.player_count(), 6);
+    }
+
+    #[test]
+    fn invalid_settings_are_refused() {
+        assert!(ServerConfig::load(parse(&["--players", "2"])).is_err());
+        assert!(Cli::try_parse_from(["wss-server", "--tls-cert", "cert.pem"]).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::game::role::RoleSet;
+use crate::types::Phase;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The mental-poker circuits take fixed-size arrays of at most this many
+/// keys and cards.
+pub const MENTAL_POKER_MAX_PLAYERS: usize = 10;
+
+/// How players learn their roles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RoleMode {
+    /// The server deals roles itself and tells each player privately.
+    #[default]
+    Plaintext,
+    /// Roles come from the jointly shuffled, encrypted deck; the server only
+    /// learns a role when its owner reveals it with a proof.
+    MentalPoker,
+}
+
+/// What happens to a shuffler who runs out of time or of attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShufflePolicy {
+    /// Pass the turn on; the deck keeps the previous shuffler's order.
+    #[default]
+    Skip,
+    /// Remove the player and send the room back to the lobby to refill the
+    /// seat; their key is part of the aggregate, so dealing cannot go on
+    /// without them.
+    Kick,
+    /// Throw away every shuffle so far and start again from the original
+    /// deck, leaving the player out of the shuffle order.
+    Restart,
+}
+
+/// How many votes it takes to lynch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VoteRule {
+    /// The single most-voted player is lynched.
+    #[default]
+    Plurality,
+    /// Only a player voted for by more than half of the living is lynched.
+    Majority,
+}
+
+/// What a vote without a single leader leads to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TieRule {
+    /// Nobody is lynched.
+    #[default]
+    NoLynch,
+    /// The tied players face a second vote; a tie there means no lynch.
+    Runoff,
+}
+
+/// Per-room game settings, chosen by the host while in the lobby.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub roles: RoleSet,
+    pub role_mode: RoleMode,
+    /// Seconds the night lasts before it resolves with whatever actions came in.
+    pub night_duration: u64,
+    /// Seconds the day lasts before it resolves with whatever votes came in.
+    pub day_duration: u64,
+    /// Seconds a disconnected player may take to `resume` before they are
+    /// removed from the lobby or, mid-game, counted as dead.
+    pub reconnect_grace: u64,
+    /// Seconds a player has to send `shuffleDone` once it is their turn.
+    pub shuffle_turn_timeout: u64,
+    /// Invalid shuffle proofs a player may send before their turn is lost.
+    pub shuffle_attempts: u32,
+    pub shuffle_policy: ShufflePolicy,
+    pub vote_rule: VoteRule,
+    pub tie_rule: TieRule,
+    /// Seconds players have at the end of a mental-poker game to prove
+    /// their card with `revealCard`.
+    pub reveal_duration: u64,
+    /// Seconds full-view spectators lag behind the game.
+    pub spectator_delay: u64,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            roles: RoleSet::for_players(4).expect("4 players is a supported size"),
+            role_mode: RoleMode::default(),
+            night_duration: 30,
+            day_duration: 60,
+            reconnect_grace: 30,
+            shuffle_turn_timeout: 60,
+            shuffle_attempts: 3,
+            shuffle_policy: ShufflePolicy::default(),
+            vote_rule: VoteRule::default(),
+            tie_rule: TieRule::default(),
+            reveal_duration: 30,
+            spectator_delay: 30,
+        }
+    }
+}
+
+/// The settings a `configure` frame changes; `None` keeps the current value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigUpdate {
+    pub roles: Option<RoleSet>,
+    pub role_mode: Option<RoleMode>,
+    pub shuffle_policy: Option<ShufflePolicy>,
+    pub vote_rule: Option<VoteRule>,
+    pub tie_rule: Option<TieRule>,
+}
+
+impl GameConfig {
+    /// This config with `update` applied.
+    pub fn updated(mut self, update: ConfigUpdate) -> Self {
+        self.roles = update.roles.unwrap_or(self.roles);
+        self.role_mode = update.role_mode.unwrap_or(self.role_mode);
+        self.shuffle_policy = update.shuffle_policy.unwrap_or(self.shuffle_policy);
+        self.vote_rule = update.vote_rule.unwrap_or(self.vote_rule);
+        self.tie_rule = update.tie_rule.unwrap_or(self.tie_rule);
+        self
+    }
+
+    /// Number of seats; the game starts once this many players are ready.
+    pub fn player_count(&self) -> usize {
+        self.roles.total()
+    }
+
+    /// The role set and mode fit together.
+    pub fn validate(&self) -> Result<(), String> {
+        self.roles.validate()?;
+        if self.role_mode == RoleMode::MentalPoker && self.player_count() > MENTAL_POKER_MAX_PLAYERS
+        {
+            return Err(format!(
+                "mental-poker mode supports at most {MENTAL_POKER_MAX_PLAYERS} players"
+            ));
+        }
+        Ok(())
+    }
+
+    /// How long `phase` lasts, or `None` if it has no deadline.
+    pub fn phase_duration(&self, phase: Phase) -> Option<Duration> {
+        match phase {
+            Phase::Night => Some(Duration::from_secs(self.night_duration)),
+            Phase::Day => Some(Duration::from_secs(self.day_duration)),
+            Phase::Reveal => Some(Duration::from_secs(self.reveal_duration)),
+            Phase::Lobby | Phase::Dealing | Phase::GameOver => None,
+        }
+    }
+}
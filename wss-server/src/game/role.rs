@@ -1,23 +1,114 @@
 use crate::types::{PlayerId, Role};
 use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-pub fn assign_roles(player_ids: &[PlayerId]) -> HashMap<PlayerId, Role> {
-    let mut roles = generate_roles(player_ids.len());
-    let mut rng = rand::rng();
+pub const MIN_PLAYERS: usize = 4;
+pub const MAX_PLAYERS: usize = 12;
 
-    roles.shuffle(&mut rng);
+/// How many of each role are dealt in one game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleSet {
+    pub werewolves: usize,
+    pub seers: usize,
+    pub villagers: usize,
+}
+
+impl RoleSet {
+    /// The default composition for `player_count` players: one werewolf
+    /// per four players, one seer, everyone else a villager.
+    pub fn for_players(player_count: usize) -> Result<Self, String> {
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&player_count) {
+            return Err(format!(
+                "unsupported player count {player_count} (expected {MIN_PLAYERS}..={MAX_PLAYERS})"
+            ));
+        }
+        let werewolves = (player_count / 4).max(1);
+        let seers = 1;
+        Ok(Self {
+            werewolves,
+            seers,
+            villagers: player_count - werewolves - seers,
+        })
+    }
 
-    player_ids.iter().cloned().zip(roles.into_iter()).collect()
+    pub fn total(&self) -> usize {
+        self.werewolves + self.seers + self.villagers
+    }
+
+    /// A playable composition: a supported size, at least one werewolf,
+    /// and werewolves outnumbered by everyone else.
+    pub fn validate(&self) -> Result<(), String> {
+        let total = self.total();
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&total) {
+            return Err(format!(
+                "unsupported player count {total} (expected {MIN_PLAYERS}..={MAX_PLAYERS})"
+            ));
+        }
+        if self.werewolves == 0 {
+            return Err("at least one werewolf is required".into());
+        }
+        if self.werewolves >= total - self.werewolves {
+            return Err("werewolves must be outnumbered by the village".into());
+        }
+        Ok(())
+    }
+
+    /// One entry per card, werewolves first, then seers, then villagers.
+    pub fn roles(&self) -> Vec<Role> {
+        std::iter::repeat_n(Role::Werewolf, self.werewolves)
+            .chain(std::iter::repeat_n(Role::Seer, self.seers))
+            .chain(std::iter::repeat_n(Role::Villager, self.villagers))
+            .collect()
+    }
+
+    /// The face-up deck every shuffle starts from: one `[flag, message]`
+    /// card per role.
+    pub fn deck(&self) -> Vec<[String; 2]> {
+        self.roles()
+            .iter()
+            .map(|role| ["1".to_string(), role.card_message().to_string()])
+            .collect()
+    }
 }
 
-fn generate_roles(player_count: usize) -> Vec<Role> {
-    match player_count {
-        4 => vec![Role::Werewolf, Role::Seer, Role::Villager, Role::Villager],
-        _ => panic!("Unsupported player count: {}", player_count),
+impl Role {
+    /// The plaintext message a card of this role carries in the deck.
+    pub fn card_message(&self) -> &'static str {
+        match self {
+            Role::Werewolf => "10",
+            Role::Seer => "1",
+            Role::Villager => "2",
+        }
+    }
+
+    /// The role whose card carries `message`.
+    pub fn from_card_message(message: &str) -> Option<Role> {
+        [Role::Werewolf, Role::Seer, Role::Villager]
+            .into_iter()
+            .find(|role| role.card_message() == message)
     }
 }
 
+pub fn assign_roles(
+    player_ids: &[PlayerId],
+    role_set: &RoleSet,
+) -> Result<HashMap<PlayerId, Role>, String> {
+    if player_ids.len() != role_set.total() {
+        return Err(format!(
+            "{} players but {} roles configured",
+            player_ids.len(),
+            role_set.total()
+        ));
+    }
+    let mut roles = role_set.roles();
+    let mut rng = rand::rng();
+
+    roles.shuffle(&mut rng);
+
+    Ok(player_ids.iter().cloned().zip(roles).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,7 +124,7 @@ mod tests {
             "p4".to_string(),
         ];
 
-        let role_map = assign_roles(&player_ids);
+        let role_map = assign_roles(&player_ids, &RoleSet::for_players(4).unwrap()).unwrap();
 
         assert_eq!(role_map.len(), 4);
 
@@ -50,12 +141,53 @@ mod tests {
     #[test]
     fn test_role_assignment_randomness() {
         let ids = vec!["a".into(), "b".into(), "c".into(), "d".into()];
-        let r1 = assign_roles(&ids);
-        let r2 = assign_roles(&ids);
+        let roles = RoleSet::for_players(4).unwrap();
+        let r1 = assign_roles(&ids, &roles).unwrap();
 
-        assert_ne!(
-            r1, r2,
+        // Only 12 distinct deals exist for 4 players, so compare against
+        // several runs instead of one to keep the test from flaking.
+        assert!(
+            (0..20).any(|_| assign_roles(&ids, &roles).unwrap() != r1),
             "Role assignment should usually be different between runs"
         );
     }
+
+    #[test]
+    fn default_role_sets_are_valid_for_5_to_12() {
+        for n in 5..=MAX_PLAYERS {
+            let roles = RoleSet::for_players(n).unwrap();
+            assert_eq!(roles.total(), n);
+            assert_eq!(roles.roles().len(), n);
+            assert!(roles.validate().is_ok(), "{n} players: {roles:?}");
+        }
+        assert_eq!(RoleSet::for_players(12).unwrap().werewolves, 3);
+    }
+
+    #[test]
+    fn deck_carries_one_card_per_role() {
+        let deck = RoleSet::for_players(4).unwrap().deck();
+        let messages: Vec<&str> = deck.iter().map(|card| card[1].as_str()).collect();
+
+        assert_eq!(messages, vec!["10", "1", "2", "2"]);
+        assert!(deck.iter().all(|card| card[0] == "1"));
+    }
+
+    #[test]
+    fn unsupported_sizes_are_rejected() {
+        assert!(RoleSet::for_players(3).is_err());
+        assert!(RoleSet::for_players(13).is_err());
+
+        let ids: Vec<PlayerId> = (0..5).map(|i| format!("p{i}")).collect();
+        assert!(assign_roles(&ids, &RoleSet::for_players(4).unwrap()).is_err());
+    }
+
+    #[test]
+    fn wolf_heavy_sets_are_rejected() {
+        let roles = RoleSet {
+            werewolves: 3,
+            seers: 1,
+            villagers: 2,
+        };
+        assert!(roles.validate().is_err());
+    }
 }
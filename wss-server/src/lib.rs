@@ -1,38 +1,29 @@
+pub mod clock;
+pub mod config;
 pub mod game;
 pub mod message;
+pub mod prover;
 pub mod room;
+pub mod server;
+pub mod tls;
 pub mod types;
-pub mod utils;
 pub mod ws;
 
-use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpServer};
-use actix_web_actors::ws as actix_ws;
-use uuid::Uuid;
+use config::ServerConfig;
+use prover::SharedBackend;
+pub use server::{RunningServer, ServerBuilder, ShutdownHandle};
 
-use room::room::{Room, SharedRoom};
-use std::sync::{Arc, Mutex};
-use ws::client::WsClient;
-
-async fn ws_handler(
-    req: HttpRequest,
-    stream: web::Payload,
-    room: web::Data<SharedRoom>,
-) -> Result<HttpResponse, Error> {
-    let id = Uuid::new_v4().to_string();
-    let client = WsClient::new(id, room.get_ref().clone());
-    actix_ws::start(client, &req, stream)
+/// Start the server described by `config`, verifying proofs with the
+/// prover server it names.
+pub async fn run_on(config: &ServerConfig) -> std::io::Result<RunningServer> {
+    ServerBuilder::new(config.clone()).build()
 }
 
-pub async fn run_on(bind_addr: &str) -> std::io::Result<actix_web::dev::Server> {
-    let room: SharedRoom = Arc::new(Mutex::new(Room::new()));
-
-    let server = HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(room.clone()))
-            .route("/ws", web::get().to(ws_handler))
-    })
-    .bind(bind_addr)?
-    .run();
-
-    Ok(server)
+/// Like [`run_on`], but verifying proofs with `backend` instead of the
+/// configured prover server.
+pub async fn run_with_backend(
+    config: &ServerConfig,
+    backend: SharedBackend,
+) -> std::io::Result<RunningServer> {
+    ServerBuilder::new(config.clone()).backend(backend).build()
 }
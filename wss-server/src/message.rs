@@ -1,6 +1,11 @@
-use crate::types::PlayerId;
-use serde::Deserialize;
+use crate::game::{
+    config::{ConfigUpdate, RoleMode, ShufflePolicy, TieRule, VoteRule},
+    role::RoleSet,
+};
+use crate::types::{Phase, PlayerId, Role, RoomId};
+use serde::{Deserialize, Serialize};
 use serde_json;
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize)]
 pub struct Incoming {
@@ -14,28 +19,45 @@ pub struct Incoming {
 pub enum ClientEvent {
     Join {
         name: String,
+        room_id: Option<RoomId>,
     },
+    Resume {
+        token: String,
+    },
+    /// Watch a room without taking a seat.
+    Spectate {
+        room_id: RoomId,
+        full_view: bool,
+    },
+    CreateRoom,
+    ListRooms,
+    Configure(ConfigUpdate),
     Ready(bool),
     Chat {
         text: String,
+        channel: ChatChannel,
     },
     NightAction {
         action: String,
         target: String,
-        proof: String,
-        public_inputs: Vec<String>,
     },
     Vote {
         target: String,
     },
+    Abstain,
+    RetractVote,
     RegisterPublicKey {
         public_key: String,
     },
+    /// The answer to a `keyChallenge`.
+    SignChallenge {
+        signature: String,
+    },
     ShuffleDone {
         encrypted_deck: Vec<[String; 2]>,
-        public_inputs: Vec<String>,
         proof: String,
     },
+    RetryAggregation,
     PickCard {
         card: usize,
     },
@@ -44,6 +66,16 @@ pub enum ClientEvent {
         card: usize,
         partial: [String; 2],
         component: String,
+        proof: String,
+    },
+    RevealRole {
+        role: Role,
+        proof: String,
+    },
+    /// End-of-game proof of the card the player was dealt.
+    RevealCard {
+        role: Role,
+        proof: String,
     },
     RawUnknown,
 }
@@ -52,19 +84,121 @@ pub fn to_client_event(msg: Incoming) -> Result<ClientEvent, String> {
     let _frame_type = msg.frame_type;
     match msg.target.as_str() {
         "join" => {
-            let payload = msg.arguments.get(0).ok_or("join expects 1 argument")?;
+            let payload = msg.arguments.first().ok_or("join expects 1 argument")?;
             #[derive(Deserialize)]
             struct JoinPayload {
                 name: String,
+                #[serde(rename = "roomId")]
+                room_id: Option<RoomId>,
             }
-            let JoinPayload { name } = serde_json::from_value(payload.clone())
+            let JoinPayload { name, room_id } = serde_json::from_value(payload.clone())
                 .map_err(|e| format!("bad join payload: {e}"))?;
-            Ok(ClientEvent::Join { name })
+            Ok(ClientEvent::Join { name, room_id })
+        }
+        "spectate" => {
+            let payload = msg.arguments.first().ok_or("spectate expects 1 argument")?;
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct SpectatePayload {
+                room_id: RoomId,
+                #[serde(default)]
+                full_view: bool,
+            }
+            let SpectatePayload { room_id, full_view } = serde_json::from_value(payload.clone())
+                .map_err(|e| format!("bad spectate payload: {e}"))?;
+            Ok(ClientEvent::Spectate { room_id, full_view })
+        }
+        "configure" => {
+            let payload = msg
+                .arguments
+                .first()
+                .ok_or("configure expects 1 argument")?;
+            #[derive(Deserialize)]
+            struct ConfigurePayload {
+                players: Option<usize>,
+                werewolves: Option<usize>,
+                seers: Option<usize>,
+                villagers: Option<usize>,
+                mode: Option<RoleMode>,
+                #[serde(rename = "shufflePolicy")]
+                shuffle_policy: Option<ShufflePolicy>,
+                #[serde(rename = "voteRule")]
+                vote_rule: Option<VoteRule>,
+                #[serde(rename = "tieRule")]
+                tie_rule: Option<TieRule>,
+            }
+            let p: ConfigurePayload = serde_json::from_value(payload.clone())
+                .map_err(|e| format!("bad configure payload: {e}"))?;
+            let roles = match (p.werewolves, p.seers, p.villagers, p.players) {
+                (Some(werewolves), Some(seers), Some(villagers), _) => Some(RoleSet {
+                    werewolves,
+                    seers,
+                    villagers,
+                }),
+                (None, None, None, Some(players)) => Some(RoleSet::for_players(players)?),
+                (None, None, None, None) => None,
+                _ => {
+                    return Err(
+                        "configure expects {players} or {werewolves,seers,villagers}".into(),
+                    )
+                }
+            };
+            Ok(ClientEvent::Configure(ConfigUpdate {
+                roles,
+                role_mode: p.mode,
+                shuffle_policy: p.shuffle_policy,
+                vote_rule: p.vote_rule,
+                tie_rule: p.tie_rule,
+            }))
         }
+        "resume" => {
+            let token = msg
+                .arguments
+                .first()
+                .and_then(|v| v.get("token"))
+                .and_then(|v| v.as_str())
+                .ok_or("resume expects {token:string}")?;
+            Ok(ClientEvent::Resume {
+                token: token.to_string(),
+            })
+        }
+        "revealRole" => {
+            let payload = msg
+                .arguments
+                .first()
+                .ok_or("revealRole expects 1 argument")?;
+            // Any `public_inputs` the client sends are ignored: the server
+            // builds them from the player's card and the claimed role.
+            #[derive(Deserialize)]
+            struct RevealPayload {
+                role: Role,
+                proof: String,
+            }
+            let RevealPayload { role, proof } = serde_json::from_value(payload.clone())
+                .map_err(|e| format!("bad revealRole payload: {e}"))?;
+            Ok(ClientEvent::RevealRole { role, proof })
+        }
+        "revealCard" => {
+            let payload = msg
+                .arguments
+                .first()
+                .ok_or("revealCard expects 1 argument")?;
+            #[derive(Deserialize)]
+            struct RevealCardPayload {
+                role: Role,
+                proof: String,
+            }
+            let RevealCardPayload { role, proof } = serde_json::from_value(payload.clone())
+                .map_err(|e| format!("bad revealCard payload: {e}"))?;
+            Ok(ClientEvent::RevealCard { role, proof })
+        }
+        "retryAggregation" => Ok(ClientEvent::RetryAggregation),
+        "createRoom" => Ok(ClientEvent::CreateRoom),
+        "listRooms" => Ok(ClientEvent::ListRooms),
         "ready" => {
             let flag = msg
                 .arguments
-                .get(0)
+                .first()
                 .and_then(|v| v.as_bool())
                 .ok_or("ready expects a bool")?;
             Ok(ClientEvent::Ready(flag))
@@ -73,66 +207,60 @@ pub fn to_client_event(msg: Incoming) -> Result<ClientEvent, String> {
             #[derive(Deserialize)]
             struct ChatPayload {
                 text: String,
+                #[serde(default)]
+                channel: ChatChannel,
             }
-            let payload = msg.arguments.get(0).ok_or("chat expects 1 argument")?;
-            let ChatPayload { text } = serde_json::from_value(payload.clone())
+            let payload = msg.arguments.first().ok_or("chat expects 1 argument")?;
+            let ChatPayload { text, channel } = serde_json::from_value(payload.clone())
                 .map_err(|e| format!("bad chat payload: {e}"))?;
-            Ok(ClientEvent::Chat { text })
+            Ok(ClientEvent::Chat { text, channel })
         }
         "pickCard" => {
             let idx = msg
                 .arguments
-                .get(0)
+                .first()
                 .and_then(|v| v.get("card"))
                 .and_then(|v| v.as_u64())
                 .ok_or("pickCard expects {card:uint}")? as usize;
             Ok(ClientEvent::PickCard { card: idx })
         }
         "decryptCard" => {
-            let obj = msg
+            let payload = msg
                 .arguments
-                .get(0)
-                .and_then(|v| v.as_object())
-                .ok_or("decryptCard expects an object")?;
-
-            let for_player = obj
-                .get("for")
-                .and_then(|v| v.as_str())
-                .ok_or("decryptCard.for missing")?
-                .to_owned();
-
-            let card = obj
-                .get("card")
-                .and_then(|v| v.as_u64())
-                .ok_or("decryptCard.card")? as usize;
-
-            let partial_arr = obj
-                .get("partial")
-                .and_then(|v| v.as_array())
-                .ok_or("decryptCard.partial")?;
-            let partial = [
-                partial_arr[0].as_str().unwrap().to_owned(),
-                partial_arr[1].as_str().unwrap().to_owned(),
-            ];
-
-            let component = obj
-                .get("component")
-                .and_then(|v| v.as_str())
-                .ok_or("decryptCard.component")?
-                .to_owned();
-
+                .first()
+                .ok_or("decryptCard expects 1 argument")?;
+            #[derive(Deserialize)]
+            struct DecryptPayload {
+                #[serde(rename = "for")]
+                for_player: PlayerId,
+                card: usize,
+                partial: [String; 2],
+                component: String,
+                proof: String,
+            }
+            let DecryptPayload {
+                for_player,
+                card,
+                partial,
+                component,
+                proof,
+            } = serde_json::from_value(payload.clone())
+                .map_err(|e| format!("bad decryptCard payload: {e}"))?;
             Ok(ClientEvent::DecryptCard {
                 for_player,
                 card,
                 partial,
                 component,
+                proof,
             })
         }
         "nightAction" => {
             let payload = msg
                 .arguments
-                .get(0)
+                .first()
                 .ok_or("nightAction expects 1 argument")?;
+            // Any `proof` or `public_inputs` the client sends are ignored:
+            // the actor's proven role is what allows the action.
             #[derive(Deserialize)]
             struct NightPayload {
                 action: String,
@@ -140,76 +268,62 @@ pub fn to_client_event(msg: Incoming) -> Result<ClientEvent, String> {
             }
             let NightPayload { action, target } = serde_json::from_value(payload.clone())
                 .map_err(|e| format!("bad nightAction payload: {e}"))?;
-            Ok(ClientEvent::NightAction {
-                action,
-                target,
-                proof: String::new(),
-                public_inputs: Vec::new(),
-            })
+            Ok(ClientEvent::NightAction { action, target })
         }
         "vote" => {
             let tgt = msg
                 .arguments
-                .get(0)
+                .first()
                 .and_then(|v| v.as_str())
                 .ok_or("vote expects a string target")?;
             Ok(ClientEvent::Vote {
                 target: tgt.to_string(),
             })
         }
+        "abstain" => Ok(ClientEvent::Abstain),
+        "retractVote" => Ok(ClientEvent::RetractVote),
         "registerPublicKey" => {
             let pk = msg
                 .arguments
-                .get(0)
+                .first()
                 .and_then(|v| v.as_str())
                 .ok_or("registerPublicKey expects a string public key")?;
             Ok(ClientEvent::RegisterPublicKey {
                 public_key: pk.to_string(),
             })
         }
+        "signChallenge" => {
+            let payload = msg
+                .arguments
+                .first()
+                .ok_or("signChallenge expects 1 argument")?;
+            #[derive(Deserialize)]
+            struct SignaturePayload {
+                signature: String,
+            }
+            let SignaturePayload { signature } = serde_json::from_value(payload.clone())
+                .map_err(|e| format!("bad signChallenge payload: {e}"))?;
+            Ok(ClientEvent::SignChallenge { signature })
+        }
         "shuffleDone" => {
-            // arguments[0] is expected to be an object with three fields
-            let obj = msg
+            let payload = msg
                 .arguments
-                .get(0)
-                .and_then(|v| v.as_object())
-                .ok_or("shuffleDone expects an object payload")?;
-
-            // 1) encrypted_deck: array of [flag, role] pairs
-            let enc = obj
-                .get("encrypted_deck")
-                .and_then(|v| v.as_array())
-                .ok_or("shuffleDone.encrypted_deck missing or not an array")?;
-            let encrypted_deck: Vec<[String; 2]> = enc
-                .iter()
-                .map(|entry| {
-                    let arr = entry
-                        .as_array()
-                        .expect("each deck entry must be a 2-element array");
-                    let a = arr[0].as_str().unwrap().to_string();
-                    let b = arr[1].as_str().unwrap().to_string();
-                    [a, b]
-                })
-                .collect();
-
-            // 2) public_inputs: array of hex strings
-            let pi = obj
-                .get("public_inputs")
-                .and_then(|v| v.as_array())
-                .ok_or("shuffleDone.public_inputs missing or not an array")?;
-            let public_inputs: Vec<String> =
-                pi.iter().map(|v| v.as_str().unwrap().to_string()).collect();
-
-            // 3) proof: single string
-            let proof = obj
-                .get("proof")
-                .and_then(|v| v.as_str())
-                .ok_or("shuffleDone.proof missing or not a string")?
-                .to_string();
-
+                .first()
+                .ok_or("shuffleDone expects 1 argument")?;
+            // Any `public_inputs` the client sends are ignored: the server
+            // builds them from the room's deck and aggregate key.
+            #[derive(Deserialize)]
+            struct ShufflePayload {
+                encrypted_deck: Vec<[String; 2]>,
+                proof: String,
+            }
+            let ShufflePayload {
+                encrypted_deck,
+                proof,
+            } = serde_json::from_value(payload.clone())
+                .map_err(|e| format!("bad shuffleDone payload: {e}"))?;
             Ok(ClientEvent::ShuffleDone {
                 encrypted_deck,
-                public_inputs,
                 proof,
             })
         }
@@ -217,6 +331,462 @@ pub fn to_client_event(msg: Incoming) -> Result<ClientEvent, String> {
     }
 }
 
+/// Every frame the server sends. Serialized through [`ServerEvent::to_frame`]
+/// it becomes the usual `{type: 1, target, arguments: [payload]}` envelope,
+/// with the variant name (camelCase) as `target`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "target", content = "arguments", rename_all = "camelCase")]
+pub enum ServerEvent {
+    RoomCreated(#[serde(with = "single")] RoomRef),
+    Rooms(#[serde(with = "single")] RoomList),
+    Joined(#[serde(with = "single")] Joined),
+    JoinFailed(#[serde(with = "single")] Reason),
+    Resumed(#[serde(with = "single")] Snapshot),
+    ResumeFailed(#[serde(with = "single")] Reason),
+    Spectating(#[serde(with = "single")] SpectatorState),
+    SpectatorView(#[serde(with = "single")] FullView),
+    Lobby(#[serde(with = "single")] LobbyState),
+    PlayerDisconnected(#[serde(with = "single")] PlayerRef),
+    PlayerReconnected(#[serde(with = "single")] PlayerRef),
+    PlayerLeft(#[serde(with = "single")] PlayerRef),
+    GameStart(#[serde(with = "single")] GameStart),
+    AggregationFailed(#[serde(with = "single")] Reason),
+    KeyChallenge(#[serde(with = "single")] KeyChallenge),
+    PublicKeyRegistered(#[serde(with = "single")] Ack),
+    StartShuffle(#[serde(with = "single")] ShuffleTurn),
+    ShuffleRejected(#[serde(with = "single")] Reason),
+    ShuffleComplete(#[serde(with = "single")] ShuffledDeck),
+    ShuffleAborted(#[serde(with = "single")] ShuffleFault),
+    ShuffleRestarted(#[serde(with = "single")] ShuffleFault),
+    CardTaken(#[serde(with = "single")] CardTaken),
+    CardsTaken(#[serde(with = "single")] TakenCards),
+    NeedDecrypt(#[serde(with = "single")] DecryptRequest),
+    PartialReady(#[serde(with = "single")] PartialDecryption),
+    AllPartsReady(#[serde(with = "single")] CardRef),
+    DecryptionFlagged(#[serde(with = "single")] FlaggedDecryption),
+    Role(#[serde(with = "single")] RoleCard),
+    RoleRejected(#[serde(with = "single")] Reason),
+    Phase(#[serde(with = "single")] PhaseChange),
+    NightAck(#[serde(with = "single")] NightAck),
+    PeekResult(#[serde(with = "single")] PeekResult),
+    NightEnd(#[serde(with = "single")] NightEnd),
+    VoteUpdate(#[serde(with = "single")] VoteTally),
+    Runoff(#[serde(with = "single")] RunoffVote),
+    DayEnd(#[serde(with = "single")] DayEnd),
+    GameOver(#[serde(with = "single")] GameOver),
+    Chat(#[serde(with = "single")] ChatLine),
+    Error(#[serde(with = "single")] ErrorReply),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<E> {
+    #[serde(rename = "type")]
+    frame_type: u8,
+    #[serde(flatten)]
+    event: E,
+}
+
+impl ServerEvent {
+    /// The JSON text frame sent over the socket.
+    pub fn to_frame(&self) -> String {
+        serde_json::to_string(&Envelope {
+            frame_type: 1,
+            event: self,
+        })
+        .expect("server events always serialize")
+    }
+
+    /// The reply to a client frame the server refused to act on.
+    pub fn error(code: ErrorCode, target: &str, message: impl Into<String>) -> Self {
+        ServerEvent::Error(ErrorReply {
+            code,
+            target: target.to_string(),
+            message: message.into(),
+        })
+    }
+
+    /// Parse a frame produced by [`ServerEvent::to_frame`].
+    pub fn from_frame(raw: &str) -> Result<Self, String> {
+        serde_json::from_str::<Envelope<ServerEvent>>(raw)
+            .map(|envelope| envelope.event)
+            .map_err(|e| format!("bad server frame: {e}"))
+    }
+}
+
+/// Frame payloads travel as the single element of `arguments`.
+mod single {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+        [value].serialize(s)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<T, D::Error> {
+        let [value] = <[T; 1]>::deserialize(d)?;
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AckStatus {
+    Ok,
+    Rejected,
+    Denied,
+}
+
+/// Why a client frame was refused, for clients to branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// Not JSON, or not a `{type, target, arguments}` frame.
+    MalformedJson,
+    /// The arguments do not match what `target` expects.
+    BadPayload,
+    UnknownTarget,
+    /// The frame needs a seat in a room and the client has none.
+    NotInRoom,
+    AlreadySeated,
+    /// Not accepted in the current phase of the game.
+    WrongPhase,
+    NotYourTurn,
+    /// A relay step arrived before the steps it depends on.
+    OutOfOrder,
+    /// The sender may not do this (not the host, dead, wrong role, ...).
+    NotAllowed,
+    /// The attached proof could not be checked; the frame may be re-sent.
+    VerifierError,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReply {
+    pub code: ErrorCode,
+    /// The `target` of the refused frame, empty if it could not be read.
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reason {
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ack {
+    pub status: AckStatus,
+}
+
+/// The nonce a player signs to prove they hold the key they registered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyChallenge {
+    pub nonce: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomRef {
+    pub room_id: RoomId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomList {
+    pub rooms: Vec<RoomSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSummary {
+    pub room_id: RoomId,
+    pub players: usize,
+    pub phase: Phase,
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Joined {
+    pub room_id: RoomId,
+    pub player_id: PlayerId,
+    pub session_token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerRef {
+    pub player_id: PlayerId,
+}
+
+/// Everything a resuming client needs to rebuild its view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub room_id: RoomId,
+    pub player_id: PlayerId,
+    pub session_token: String,
+    pub phase: Phase,
+    pub round: u32,
+    pub deadline: Option<u64>,
+    pub players: Vec<PlayerStatus>,
+    pub alive: bool,
+    pub role: Option<Role>,
+    pub taken_card: Option<usize>,
+    pub decrypt: Option<DecryptProgress>,
+}
+
+/// What a new spectator sees of the room: public state only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpectatorState {
+    pub room_id: RoomId,
+    pub phase: Phase,
+    pub round: u32,
+    pub deadline: Option<u64>,
+    pub players: Vec<PlayerStatus>,
+    /// Seconds `spectatorView` frames lag behind the game, if this
+    /// spectator asked for the full view.
+    pub delay: Option<u64>,
+}
+
+/// Full-information view for spectators, sent a while after the fact so it
+/// cannot be relayed to players in time to matter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FullView {
+    pub phase: Phase,
+    pub round: u32,
+    pub players: Vec<PlayerRole>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerRole {
+    pub id: PlayerId,
+    pub role: Option<Role>,
+    pub alive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStatus {
+    pub id: PlayerId,
+    pub name: String,
+    pub alive: bool,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecryptProgress {
+    pub card: usize,
+    pub components: Vec<String>,
+    pub pending_helpers: Vec<PlayerId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LobbyState {
+    pub room_id: RoomId,
+    pub host: Option<PlayerId>,
+    pub config: LobbyConfig,
+    pub players: Vec<LobbyPlayer>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LobbyConfig {
+    pub mode: RoleMode,
+    pub shuffle_policy: ShufflePolicy,
+    pub vote_rule: VoteRule,
+    pub tie_rule: TieRule,
+    pub players: usize,
+    pub werewolves: usize,
+    pub seers: usize,
+    pub villagers: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LobbyPlayer {
+    pub id: PlayerId,
+    pub name: String,
+    pub ready: bool,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameStart {
+    pub players: Vec<PlayerInfo>,
+    pub mode: RoleMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub id: PlayerId,
+    pub name: String,
+    /// Position in the agreed seat order, starting at 0.
+    pub seat: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShuffleTurn {
+    pub agg_pk: String,
+    pub deck: Vec<[String; 2]>,
+}
+
+/// A shuffler lost their turn; `policy` says what the room did about it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShuffleFault {
+    pub player_id: PlayerId,
+    pub reason: String,
+    pub policy: ShufflePolicy,
+    /// Shuffle order from here on.
+    pub order: Vec<PlayerId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShuffledDeck {
+    pub deck: Vec<[String; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardTaken {
+    pub status: AckStatus,
+    pub card: usize,
+}
+
+/// Which deck indices are gone, without saying who took them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TakenCards {
+    pub taken: Vec<usize>,
+    /// Players who still have to pick.
+    pub remaining: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardRef {
+    pub card: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecryptRequest {
+    #[serde(rename = "for")]
+    pub for_player: PlayerId,
+    pub card: usize,
+    pub cipher: [String; 2],
+}
+
+/// A helper relayed a partial decryption whose proof did not verify.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlaggedDecryption {
+    pub player_id: PlayerId,
+    #[serde(rename = "for")]
+    pub for_player: PlayerId,
+    pub card: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartialDecryption {
+    pub card: usize,
+    pub partial: [String; 2],
+    pub component: String,
+    pub components: Vec<String>,
+}
+
+/// Private `role` frame; werewolves also learn who their packmates are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoleCard {
+    pub role: Role,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub werewolves: Option<Vec<PlayerId>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseChange {
+    pub phase: Phase,
+    pub round: u32,
+    /// Seconds the phase lasts, `None` for untimed phases.
+    pub duration: Option<u64>,
+    /// Unix milliseconds at which the phase times out.
+    pub deadline: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NightAck {
+    pub status: AckStatus,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeekResult {
+    pub target: PlayerId,
+    pub role: Option<Role>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NightEnd {
+    pub killed: Option<PlayerId>,
+}
+
+/// The running day vote, in seat order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoteTally {
+    /// Players with at least one vote against them.
+    pub votes: Vec<VoteCount>,
+    pub ballots: Vec<Ballot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteCount {
+    pub player_id: PlayerId,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ballot {
+    pub voter: PlayerId,
+    /// `None` for an abstention.
+    pub target: Option<PlayerId>,
+}
+
+/// The day vote tied and starts over between these players only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunoffVote {
+    pub candidates: Vec<PlayerId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DayEnd {
+    pub lynched: Option<PlayerId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameOver {
+    pub winner: String,
+    pub roles: HashMap<PlayerId, Option<Role>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatLine {
+    pub from: PlayerId,
+    pub text: String,
+    pub channel: ChatChannel,
+}
+
+/// Who a chat line is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChatChannel {
+    /// Every living player; closed at night.
+    #[default]
+    Public,
+    /// Living werewolves, day and night.
+    Wolves,
+    /// The graveyard: dead players only.
+    Dead,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,7 +803,255 @@ mod tests {
         let inc: Incoming = serde_json::from_value(frame).unwrap();
         let evt = crate::message::to_client_event(inc).unwrap();
 
-        assert!(matches!(evt, ClientEvent::Join { name } if name == "Effy"));
+        assert!(matches!(evt, ClientEvent::Join { name, room_id: None } if name == "Effy"));
+    }
+
+    #[test]
+    fn join_with_room_id_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "join",
+            "arguments": [ { "name": "Effy", "roomId": "AB12CD" } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::Join { name, room_id: Some(room) } if name == "Effy" && room == "AB12CD"
+        ));
+    }
+
+    #[test]
+    fn configure_by_player_count_uses_default_roles() {
+        let frame = json!({
+            "type": 1,
+            "target": "configure",
+            "arguments": [ { "players": 8 } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::Configure(ConfigUpdate { roles: Some(roles), role_mode: None, .. })
+                if roles == RoleSet::for_players(8).unwrap()
+        ));
+    }
+
+    #[test]
+    fn configure_with_explicit_roles_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "configure",
+            "arguments": [ { "werewolves": 2, "seers": 1, "villagers": 3 } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::Configure(ConfigUpdate { roles: Some(roles), .. })
+                if roles.werewolves == 2 && roles.total() == 6
+        ));
+    }
+
+    #[test]
+    fn configure_mode_alone_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "configure",
+            "arguments": [ { "mode": "mentalPoker" } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::Configure(ConfigUpdate {
+                roles: None,
+                role_mode: Some(RoleMode::MentalPoker),
+                shuffle_policy: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn configure_vote_rules_parse() {
+        let frame = json!({
+            "type": 1,
+            "target": "configure",
+            "arguments": [ { "voteRule": "majority", "tieRule": "runoff" } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::Configure(ConfigUpdate {
+                roles: None,
+                vote_rule: Some(VoteRule::Majority),
+                tie_rule: Some(TieRule::Runoff),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn configure_shuffle_policy_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "configure",
+            "arguments": [ { "shufflePolicy": "restart" } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::Configure(ConfigUpdate {
+                roles: None,
+                role_mode: None,
+                shuffle_policy: Some(ShufflePolicy::Restart),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reveal_role_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "revealRole",
+            "arguments": [ { "role": "Seer", "proof": "0xab", "public_inputs": ["1", "2"] } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::RevealRole { role: Role::Seer, proof } if proof == "0xab"
+        ));
+    }
+
+    #[test]
+    fn chat_channel_defaults_to_public() {
+        let parse = |args: serde_json::Value| {
+            let inc: Incoming =
+                serde_json::from_value(json!({"type": 1, "target": "chat", "arguments": [args]}))
+                    .unwrap();
+            crate::message::to_client_event(inc).unwrap()
+        };
+
+        assert!(matches!(
+            parse(json!({"text": "hi"})),
+            ClientEvent::Chat {
+                channel: ChatChannel::Public,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse(json!({"text": "hi", "channel": "wolves"})),
+            ClientEvent::Chat {
+                channel: ChatChannel::Wolves,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn spectate_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "spectate",
+            "arguments": [ { "roomId": "ABC123", "fullView": true } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::Spectate { room_id, full_view: true } if room_id == "ABC123"
+        ));
+    }
+
+    #[test]
+    fn reveal_card_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "revealCard",
+            "arguments": [ { "role": "Werewolf", "proof": "0xab" } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::RevealCard { role: Role::Werewolf, proof } if proof == "0xab"
+        ));
+    }
+
+    #[test]
+    fn night_action_ignores_proof() {
+        let frame = json!({
+            "type": 1,
+            "target": "nightAction",
+            "arguments": [ {
+                "action": "kill",
+                "target": "p2",
+                "proof": "0xdead",
+                "public_inputs": ["10", "0"]
+            } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::NightAction { action, target } if action == "kill" && target == "p2"
+        ));
+    }
+
+    #[test]
+    fn night_action_without_proof_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "nightAction",
+            "arguments": [ { "action": "peek", "target": "p3" } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(
+            evt,
+            ClientEvent::NightAction { action, target } if action == "peek" && target == "p3"
+        ));
+    }
+
+    #[test]
+    fn resume_event_parses() {
+        let frame = json!({
+            "type": 1,
+            "target": "resume",
+            "arguments": [ { "token": "abc" } ]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = crate::message::to_client_event(inc).unwrap();
+
+        assert!(matches!(evt, ClientEvent::Resume { token } if token == "abc"));
     }
 
     #[test]
@@ -250,6 +1068,47 @@ mod tests {
         assert!(matches!(evt, ClientEvent::Ready(true)));
     }
 
+    #[test]
+    fn decrypt_card_requires_a_proof() {
+        let mut frame = json!({
+            "type": 1,
+            "target": "decryptCard",
+            "arguments": [{
+                "for": "p1",
+                "card": 2,
+                "partial": ["x", "y"],
+                "component": "c"
+            }]
+        });
+
+        let inc: Incoming = serde_json::from_value(frame.clone()).unwrap();
+        assert!(to_client_event(inc).is_err());
+
+        frame["arguments"][0]["proof"] = json!("0xproof");
+        let inc: Incoming = serde_json::from_value(frame).unwrap();
+        let evt = to_client_event(inc).unwrap();
+        assert!(matches!(evt, ClientEvent::DecryptCard { proof, .. } if proof == "0xproof"));
+    }
+
+    #[test]
+    fn decrypt_card_with_non_string_partial_is_refused() {
+        for partial in [json!([1, 2]), json!(["x"]), json!([])] {
+            let frame = json!({
+                "type": 1,
+                "target": "decryptCard",
+                "arguments": [{
+                    "for": "p1",
+                    "card": 2,
+                    "partial": partial,
+                    "component": "c",
+                    "proof": "0xproof"
+                }]
+            });
+            let inc: Incoming = serde_json::from_value(frame).unwrap();
+            assert!(to_client_event(inc).is_err());
+        }
+    }
+
     #[test]
     fn unknown_target_is_raw_unknown() {
         let frame = json!({
@@ -263,4 +1122,130 @@ mod tests {
 
         assert!(matches!(evt, ClientEvent::RawUnknown));
     }
+
+    fn round_trip(event: ServerEvent) -> serde_json::Value {
+        let frame = event.to_frame();
+        assert_eq!(ServerEvent::from_frame(&frame).unwrap(), event);
+        serde_json::from_str(&frame).unwrap()
+    }
+
+    #[test]
+    fn server_events_use_the_frame_envelope() {
+        let value = round_trip(ServerEvent::Joined(Joined {
+            room_id: "ABC123".into(),
+            player_id: "p1".into(),
+            session_token: "tok".into(),
+        }));
+
+        assert_eq!(
+            value,
+            json!({
+                "type": 1,
+                "target": "joined",
+                "arguments": [{ "roomId": "ABC123", "playerId": "p1", "sessionToken": "tok" }]
+            })
+        );
+    }
+
+    #[test]
+    fn server_events_keep_wire_field_names() {
+        let shuffle = round_trip(ServerEvent::StartShuffle(ShuffleTurn {
+            agg_pk: "agg".into(),
+            deck: vec![["1".into(), "10".into()]],
+        }));
+        assert_eq!(shuffle["target"], "startShuffle");
+        assert_eq!(shuffle["arguments"][0]["agg_pk"], "agg");
+
+        let decrypt = round_trip(ServerEvent::NeedDecrypt(DecryptRequest {
+            for_player: "p2".into(),
+            card: 3,
+            cipher: ["a".into(), "b".into()],
+        }));
+        assert_eq!(decrypt["arguments"][0]["for"], "p2");
+
+        let ack = round_trip(ServerEvent::NightAck(NightAck {
+            status: AckStatus::Rejected,
+            reason: "invalid proof".into(),
+        }));
+        assert_eq!(ack["arguments"][0]["status"], "rejected");
+    }
+
+    #[test]
+    fn role_frame_lists_packmates_only_for_werewolves() {
+        let seer = round_trip(ServerEvent::Role(RoleCard {
+            role: Role::Seer,
+            werewolves: None,
+        }));
+        assert!(seer["arguments"][0].get("werewolves").is_none());
+
+        let wolf = round_trip(ServerEvent::Role(RoleCard {
+            role: Role::Werewolf,
+            werewolves: Some(vec!["w1".into(), "w2".into()]),
+        }));
+        assert_eq!(wolf["arguments"][0]["werewolves"], json!(["w1", "w2"]));
+    }
+
+    #[test]
+    fn vote_update_carries_counts_and_ballots() {
+        let value = round_trip(ServerEvent::VoteUpdate(VoteTally {
+            votes: vec![VoteCount {
+                player_id: "b".into(),
+                count: 1,
+            }],
+            ballots: vec![Ballot {
+                voter: "a".into(),
+                target: Some("b".into()),
+            }],
+        }));
+
+        assert_eq!(
+            value["arguments"],
+            json!([{
+                "votes": [{ "playerId": "b", "count": 1 }],
+                "ballots": [{ "voter": "a", "target": "b" }]
+            }])
+        );
+    }
+
+    #[test]
+    fn snapshot_round_trips() {
+        round_trip(ServerEvent::Resumed(Snapshot {
+            room_id: "ROOM".into(),
+            player_id: "p1".into(),
+            session_token: "tok".into(),
+            phase: Phase::Night,
+            round: 2,
+            deadline: Some(1_000),
+            players: vec![PlayerStatus {
+                id: "p1".into(),
+                name: "P1".into(),
+                alive: true,
+                connected: true,
+            }],
+            alive: true,
+            role: Some(Role::Villager),
+            taken_card: None,
+            decrypt: None,
+        }));
+    }
+
+    #[test]
+    fn error_reply_carries_code_and_target() {
+        let value = round_trip(ServerEvent::error(
+            ErrorCode::WrongPhase,
+            "vote",
+            "votes are only accepted during the day",
+        ));
+
+        assert_eq!(value["target"], "error");
+        assert_eq!(value["arguments"][0]["code"], "wrongPhase");
+        assert_eq!(value["arguments"][0]["target"], "vote");
+    }
+
+    #[test]
+    fn from_frame_rejects_unknown_targets() {
+        let raw = json!({"type": 1, "target": "nope", "arguments": [{}]}).to_string();
+
+        assert!(ServerEvent::from_frame(&raw).is_err());
+    }
 }
@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::game::config::MENTAL_POKER_MAX_PLAYERS;
+use crate::prover::{CardClaim, DecryptionClaim, KeyClaim, ProofBackend, ProofError, ShuffleClaim};
+
+/// Circuit names as registered with the prover server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitNames {
+    pub aggregate_public_keys: String,
+    pub sign_challenge: String,
+    /// `{n}` is replaced by the deck size, e.g. `shuffle{n}` → `shuffle4`.
+    pub shuffle: String,
+    pub card_message: String,
+    pub decrypt_one_layer: String,
+    pub reveal_card: String,
+}
+
+impl Default for CircuitNames {
+    fn default() -> Self {
+        Self {
+            aggregate_public_keys: "aggregatePublicKeys".into(),
+            sign_challenge: "signChallenge".into(),
+            shuffle: "shuffle{n}".into(),
+            card_message: "verifyCardMessage".into(),
+            decrypt_one_layer: "decryptOneLayer".into(),
+            reveal_card: "revealCard".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpBackendConfig {
+    /// Base URL of the prover server; `/execute` and `/verify` are appended.
+    pub url: String,
+    /// Whole seconds in config files.
+    #[serde(with = "secs")]
+    pub timeout: Duration,
+    pub circuits: CircuitNames,
+}
+
+impl Default for HttpBackendConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:3000".into(),
+            timeout: Duration::from_secs(30),
+            circuits: CircuitNames::default(),
+        }
+    }
+}
+
+/// (De)serialize a `Duration` as whole seconds.
+mod secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExecResponse {
+    ok: bool,
+    data: ExecData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExecData {
+    outputs: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyResponse {
+    ok: bool,
+}
+
+/// Talks to the Bun prover server over HTTP.
+pub struct HttpBackend {
+    config: HttpBackendConfig,
+    agent: ureq::Agent,
+}
+
+impl Default for HttpBackend {
+    fn default() -> Self {
+        Self::new(HttpBackendConfig::default())
+    }
+}
+
+impl HttpBackend {
+    pub fn new(config: HttpBackendConfig) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+        Self { config, agent }
+    }
+
+    fn post(&self, path: &str, payload: &Value) -> Result<String, ProofError> {
+        let url = format!("{}/{}", self.config.url.trim_end_matches('/'), path);
+        let resp = self
+            .agent
+            .post(&url)
+            .set("Content-Type", "application/json")
+            .send_string(&payload.to_string())?;
+        Ok(resp.into_string()?)
+    }
+
+    fn verify(
+        &self,
+        circuit: &str,
+        public_inputs: &[String],
+        proof: &str,
+    ) -> Result<bool, ProofError> {
+        if proof.is_empty() {
+            return Ok(false);
+        }
+
+        let payload = json!({
+            "circuit_name": circuit,
+            "data": {
+                "public_inputs": public_inputs,
+                "proof": proof
+            }
+        });
+
+        let body = self.post("verify", &payload)?;
+        let v: VerifyResponse = serde_json::from_str(&body)?;
+        Ok(v.ok)
+    }
+}
+
+impl ProofBackend for HttpBackend {
+    fn aggregate_public_keys(&self, pks: &[String]) -> Result<String, ProofError> {
+        if pks.len() > MENTAL_POKER_MAX_PLAYERS {
+            return Err(format!(
+                "{} public keys exceed the circuit capacity of {}",
+                pks.len(),
+                MENTAL_POKER_MAX_PLAYERS
+            )
+            .into());
+        }
+
+        // the circuit takes a fixed-size array, padded with "0"
+        let mut padded = pks.to_vec();
+        padded.resize(MENTAL_POKER_MAX_PLAYERS, "0".into());
+
+        let payload = json!({
+            "circuit_name": self.config.circuits.aggregate_public_keys,
+            "data": {
+                "pks": padded,
+                "num_pks": pks.len().to_string(),
+            }
+        });
+
+        let body = self.post("execute", &payload)?;
+        let exec: ExecResponse = serde_json::from_str(&body)?;
+        if !exec.ok {
+            Err(format!("Circuit execution failed: {:?}", exec).into())
+        } else {
+            Ok(exec.data.outputs)
+        }
+    }
+
+    fn verify_key_signature(&self, claim: &KeyClaim, signature: &str) -> Result<bool, ProofError> {
+        self.verify(
+            &self.config.circuits.sign_challenge,
+            &claim.public_inputs(),
+            signature,
+        )
+    }
+
+    fn verify_shuffle(&self, claim: &ShuffleClaim, proof: &str) -> Result<bool, ProofError> {
+        let circuit = self
+            .config
+            .circuits
+            .shuffle
+            .replace("{n}", &claim.deck.len().to_string());
+        self.verify(&circuit, &claim.public_inputs(), proof)
+    }
+
+    fn verify_card_message(&self, claim: &CardClaim, proof: &str) -> Result<bool, ProofError> {
+        self.verify(
+            &self.config.circuits.card_message,
+            &claim.public_inputs(),
+            proof,
+        )
+    }
+
+    fn verify_card_reveal(&self, claim: &CardClaim, proof: &str) -> Result<bool, ProofError> {
+        self.verify(
+            &self.config.circuits.reveal_card,
+            &claim.public_inputs(),
+            proof,
+        )
+    }
+
+    fn verify_partial_decryption(
+        &self,
+        claim: &DecryptionClaim,
+        proof: &str,
+    ) -> Result<bool, ProofError> {
+        self.verify(
+            &self.config.circuits.decrypt_one_layer,
+            &claim.public_inputs(),
+            proof,
+        )
+    }
+}
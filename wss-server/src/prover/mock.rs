@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::prover::{CardClaim, DecryptionClaim, KeyClaim, ProofBackend, ProofError, ShuffleClaim};
+
+/// In-process stand-in for the prover server. It never checks anything:
+/// every non-empty proof is accepted (or, after `set_accept(false)`,
+/// rejected) and the aggregate key is a readable combination of the inputs.
+pub struct MockBackend {
+    accept: AtomicBool,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self {
+            accept: AtomicBool::new(true),
+        }
+    }
+
+    pub fn set_accept(&self, accept: bool) {
+        self.accept.store(accept, Ordering::SeqCst);
+    }
+
+    fn verdict(&self, proof: &str) -> bool {
+        !proof.is_empty() && self.accept.load(Ordering::SeqCst)
+    }
+}
+
+impl ProofBackend for MockBackend {
+    fn aggregate_public_keys(&self, pks: &[String]) -> Result<String, ProofError> {
+        if pks.is_empty() {
+            return Err("no public keys to aggregate".into());
+        }
+        Ok(format!("agg({})", pks.join(",")))
+    }
+
+    fn verify_key_signature(&self, _claim: &KeyClaim, signature: &str) -> Result<bool, ProofError> {
+        Ok(self.verdict(signature))
+    }
+
+    fn verify_shuffle(&self, _claim: &ShuffleClaim, proof: &str) -> Result<bool, ProofError> {
+        Ok(self.verdict(proof))
+    }
+
+    fn verify_card_message(&self, _claim: &CardClaim, proof: &str) -> Result<bool, ProofError> {
+        Ok(self.verdict(proof))
+    }
+
+    fn verify_card_reveal(&self, _claim: &CardClaim, proof: &str) -> Result<bool, ProofError> {
+        Ok(self.verdict(proof))
+    }
+
+    fn verify_partial_decryption(
+        &self,
+        _claim: &DecryptionClaim,
+        proof: &str,
+    ) -> Result<bool, ProofError> {
+        Ok(self.verdict(proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate_keeps_key_order() {
+        let backend = MockBackend::new();
+        let agg = backend
+            .aggregate_public_keys(&["3".into(), "1".into(), "2".into()])
+            .unwrap();
+
+        assert_eq!(agg, "agg(3,1,2)");
+    }
+
+    #[test]
+    fn verdict_follows_accept_flag() {
+        let backend = MockBackend::new();
+        let claim = ShuffleClaim {
+            agg_pk: "agg".into(),
+            deck: Vec::new(),
+            shuffled: Vec::new(),
+        };
+        assert!(backend.verify_shuffle(&claim, "proof").unwrap());
+        let card = CardClaim {
+            agg_pk: "agg".into(),
+            card: ["1".into(), "2".into()],
+            message: "10".into(),
+        };
+        assert!(!backend.verify_card_message(&card, "").unwrap());
+
+        backend.set_accept(false);
+        assert!(!backend.verify_shuffle(&claim, "proof").unwrap());
+    }
+}
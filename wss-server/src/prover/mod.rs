@@ -0,0 +1,130 @@
+pub mod http;
+pub mod mock;
+
+use std::sync::Arc;
+
+pub type ProofError = Box<dyn std::error::Error + Send + Sync>;
+pub type SharedBackend = Arc<dyn ProofBackend>;
+
+/// Executes the game's Noir circuits and verifies the proofs players send.
+///
+/// Implementations are called from blocking worker threads, never while a
+/// room is locked, so they are free to do slow network or CPU work.
+pub trait ProofBackend: Send + Sync {
+    /// Combine the players' ElGamal public keys, in seat order, into the
+    /// aggregate key the deck is encrypted under.
+    fn aggregate_public_keys(&self, pks: &[String]) -> Result<String, ProofError>;
+
+    /// Verify that `signature` signs `claim.nonce` with the secret key
+    /// behind `claim.public_key`.
+    fn verify_key_signature(&self, claim: &KeyClaim, signature: &str) -> Result<bool, ProofError>;
+
+    /// Verify a proof that `claim.shuffled` is a correct re-encryption and
+    /// permutation of `claim.deck`.
+    fn verify_shuffle(&self, claim: &ShuffleClaim, proof: &str) -> Result<bool, ProofError>;
+
+    /// Verify a proof that a player's taken card decrypts to the message
+    /// of the role they reveal before the first night, as described by
+    /// `claim`.
+    fn verify_card_message(&self, claim: &CardClaim, proof: &str) -> Result<bool, ProofError>;
+
+    /// Verify a proof that a player's dealt card decrypts to the message
+    /// they claim, as described by `claim`.
+    fn verify_card_reveal(&self, claim: &CardClaim, proof: &str) -> Result<bool, ProofError>;
+
+    /// Verify a proof that a helper stripped their layer of encryption off
+    /// a card correctly, as described by `claim`.
+    fn verify_partial_decryption(
+        &self,
+        claim: &DecryptionClaim,
+        proof: &str,
+    ) -> Result<bool, ProofError>;
+}
+
+/// What a player asserts by answering a key challenge. Built by the server
+/// when it hands out the nonce, so a signature over another nonce, or by
+/// another key, cannot be replayed to claim a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyClaim {
+    pub public_key: String,
+    pub nonce: String,
+}
+
+impl KeyClaim {
+    /// Public inputs of the `signChallenge` circuit, in order:
+    /// `[public_key, nonce]`.
+    pub fn public_inputs(&self) -> Vec<String> {
+        vec![self.public_key.clone(), self.nonce.clone()]
+    }
+}
+
+/// What a shuffler asserts with `shuffleDone`. Built by the server from
+/// the deck it handed out, so a proof of some other shuffle cannot be
+/// replayed to swap the deck.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShuffleClaim {
+    pub agg_pk: String,
+    pub deck: Vec<[String; 2]>,
+    pub shuffled: Vec<[String; 2]>,
+}
+
+impl ShuffleClaim {
+    /// Public inputs of the `shuffle{n}` circuit, in order: `agg_pk`, then
+    /// the input deck and the shuffled deck, each flattened card by card.
+    pub fn public_inputs(&self) -> Vec<String> {
+        std::iter::once(self.agg_pk.clone())
+            .chain(self.deck.iter().flatten().cloned())
+            .chain(self.shuffled.iter().flatten().cloned())
+            .collect()
+    }
+}
+
+/// What a helper asserts when relaying one layer of decryption. Built by
+/// the server from its own state, so a proof only passes if it is bound to
+/// the helper's registered key and the cipher they were actually sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptionClaim {
+    pub public_key: String,
+    pub cipher: [String; 2],
+    pub partial: [String; 2],
+    pub component: String,
+}
+
+impl DecryptionClaim {
+    /// Public inputs of the `decryptOneLayer` circuit, in order:
+    /// `[public_key, cipher.0, cipher.1, partial.0, partial.1, component]`.
+    pub fn public_inputs(&self) -> Vec<String> {
+        vec![
+            self.public_key.clone(),
+            self.cipher[0].clone(),
+            self.cipher[1].clone(),
+            self.partial[0].clone(),
+            self.partial[1].clone(),
+            self.component.clone(),
+        ]
+    }
+}
+
+/// What a player asserts about the card they took: that it decrypts to
+/// `message`, the message of the role they reveal. Built by the server from
+/// the final deck and the aggregate key, so the proof is bound to the card
+/// the player actually took and cannot be replayed by anyone else.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardClaim {
+    pub agg_pk: String,
+    pub card: [String; 2],
+    pub message: String,
+}
+
+impl CardClaim {
+    /// Public inputs of the `verifyCardMessage` and `revealCard` circuits,
+    /// in order: `[agg_pk, card.0, card.1, message]`.
+    pub fn public_inputs(&self) -> Vec<String> {
+        vec![
+            self.agg_pk.clone(),
+            self.card[0].clone(),
+            self.card[1].clone(),
+            self.message.clone(),
+        ]
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::config::{GameConfig, ShufflePolicy},
+    types::{Phase, PlayerId, Role},
+};
+
+/// Why a player is out of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeathCause {
+    Killed,
+    Lynched,
+    /// Did not come back within the reconnect grace period.
+    Left,
+}
+
+/// One state transition of a room, as recorded in its event log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "event",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum GameEvent {
+    PlayerJoined {
+        player_id: PlayerId,
+        name: String,
+    },
+    /// Removed from the lobby, or kicked back to it during dealing.
+    PlayerRemoved {
+        player_id: PlayerId,
+    },
+    Ready {
+        player_id: PlayerId,
+        ready: bool,
+    },
+    Configured {
+        config: GameConfig,
+    },
+    KeyRegistered {
+        player_id: PlayerId,
+        public_key: String,
+    },
+    GameStarted {
+        seats: Vec<PlayerId>,
+    },
+    /// Plaintext mode: the roles the server dealt.
+    RolesDealt {
+        roles: HashMap<PlayerId, Role>,
+    },
+    KeysAggregated {
+        agg_pk: String,
+    },
+    ShuffleAccepted {
+        player_id: PlayerId,
+        deck: Vec<[String; 2]>,
+    },
+    ShuffleFault {
+        player_id: PlayerId,
+        reason: String,
+        policy: ShufflePolicy,
+    },
+    CardPicked {
+        player_id: PlayerId,
+        card: usize,
+    },
+    RoleRevealed {
+        player_id: PlayerId,
+        role: Role,
+    },
+    PhaseStarted {
+        phase: Phase,
+        round: u32,
+    },
+    NightAction {
+        player_id: PlayerId,
+        action: String,
+        target: PlayerId,
+    },
+    /// `target` is `None` for an abstention.
+    Vote {
+        voter: PlayerId,
+        target: Option<PlayerId>,
+    },
+    VoteRetracted {
+        voter: PlayerId,
+    },
+    Died {
+        player_id: PlayerId,
+        cause: DeathCause,
+    },
+    /// Mental-poker end of game: a card proven with `revealCard`.
+    CardRevealed {
+        player_id: PlayerId,
+        role: Role,
+    },
+    GameOver {
+        winner: String,
+        roles: HashMap<PlayerId, Option<Role>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Position in the log, from 0.
+    pub seq: u64,
+    /// Unix milliseconds.
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+/// Ordered history of everything that happened in a room.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EventLog {
+    entries: Vec<LogEntry>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, at: u64, event: GameEvent) {
+        let seq = self.entries.len() as u64;
+        self.entries.push(LogEntry { seq, at, event });
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// One JSON object per line, in order.
+    pub fn to_json_lines(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).expect("log entries serialize") + "\n")
+            .collect()
+    }
+
+    /// Parse an exported log back; blank lines are skipped.
+    pub fn from_json_lines(text: &str) -> Result<Self, String> {
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(n, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {e}", n + 1)))
+            .collect::<Result<Vec<LogEntry>, String>>()?;
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.seq != index as u64 {
+                return Err(format!("entry {index} has seq {}", entry.seq));
+            }
+        }
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_lines_round_trip() {
+        let mut log = EventLog::new();
+        log.record(
+            1,
+            GameEvent::PlayerJoined {
+                player_id: "a".into(),
+                name: "Ann".into(),
+            },
+        );
+        log.record(
+            2,
+            GameEvent::Vote {
+                voter: "a".into(),
+                target: None,
+            },
+        );
+
+        let text = log.to_json_lines();
+        assert_eq!(
+            text.lines().next().unwrap(),
+            r#"{"seq":0,"at":1,"event":"playerJoined","playerId":"a","name":"Ann"}"#
+        );
+        assert_eq!(EventLog::from_json_lines(&text).unwrap(), log);
+    }
+
+    #[test]
+    fn gaps_in_the_log_are_rejected() {
+        let text = r#"{"seq":1,"at":1,"event":"voteRetracted","voter":"a"}"#;
+        assert!(EventLog::from_json_lines(text).is_err());
+    }
+}
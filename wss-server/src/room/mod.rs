@@ -1 +1,5 @@
+pub mod log;
+pub mod registry;
+#[allow(clippy::module_inception)]
 pub mod room;
+pub mod store;
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    clock::{SharedClock, SystemClock},
+    game::config::GameConfig,
+    message::RoomSummary,
+    room::{
+        room::{Room, SharedRoom},
+        store::SharedStore,
+    },
+    types::RoomId,
+};
+use rand::distr::{Alphanumeric, SampleString};
+
+pub type SharedRegistry = Arc<Mutex<RoomRegistry>>;
+
+const ROOM_CODE_LEN: usize = 6;
+/// How long a created room waits for its first player before it is dropped.
+const UNUSED_ROOM_TTL: Duration = Duration::from_secs(300);
+
+/// All live rooms of this server process, keyed by their join code.
+pub struct RoomRegistry {
+    rooms: HashMap<RoomId, SharedRoom>,
+    /// Where finished games' event logs are written, if anywhere.
+    log_dir: Option<PathBuf>,
+    /// Where rooms save their state to survive a restart, if anywhere.
+    store: Option<SharedStore>,
+    /// What new rooms start with.
+    game_config: GameConfig,
+    clock: SharedClock,
+}
+
+impl Default for RoomRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RoomRegistry {
+    pub fn new() -> Self {
+        Self {
+            rooms: HashMap::new(),
+            log_dir: None,
+            store: None,
+            game_config: GameConfig::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Let rooms tell time by `clock`. Set it before [`Self::with_store`]
+    /// so restored rooms use it too.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Start new rooms with `config` instead of the built-in defaults.
+    pub fn with_game_config(mut self, config: GameConfig) -> Self {
+        self.game_config = config;
+        self
+    }
+
+    /// Export each finished room's event log to `<dir>/<room id>.jsonl`.
+    pub fn with_log_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.log_dir = Some(dir.into());
+        self
+    }
+
+    /// Keep every room's state in `store`, and bring back the unfinished
+    /// rooms it already holds so their players can `resume`.
+    pub fn with_store(mut self, store: SharedStore) -> Self {
+        match store.load_all() {
+            Ok(snapshots) => {
+                for snapshot in snapshots {
+                    let id = snapshot.id.clone();
+                    let room = Room::restore(snapshot, self.clock.clone(), Some(store.clone()));
+                    if room.lock().unwrap().is_finished() {
+                        forget(&store, &id);
+                        continue;
+                    }
+                    self.rooms.insert(id, room);
+                }
+            }
+            Err(e) => log::warn!("RoomRegistry::with_store load failed: {}", e),
+        }
+        self.store = Some(store);
+        self
+    }
+
+    /// Create an empty room under a fresh, unused join code.
+    pub fn create(&mut self) -> (RoomId, SharedRoom) {
+        self.prune();
+        let id = loop {
+            let code = Alphanumeric
+                .sample_string(&mut rand::rng(), ROOM_CODE_LEN)
+                .to_uppercase();
+            if !self.rooms.contains_key(&code) {
+                break code;
+            }
+        };
+        log::info!("RoomRegistry::create id={}", id);
+        let room = Room::new_shared(id.clone(), self.game_config, self.clock.clone());
+        if let Some(store) = &self.store {
+            room.lock().unwrap().set_store(store.clone());
+        }
+        self.rooms.insert(id.clone(), room.clone());
+        (id, room)
+    }
+
+    pub fn get(&mut self, id: &str) -> Option<SharedRoom> {
+        self.prune();
+        self.rooms.get(id).cloned()
+    }
+
+    /// Any room still accepting players, or a new one if none is open.
+    pub fn find_open(&mut self) -> (RoomId, SharedRoom) {
+        self.prune();
+        let open = self
+            .rooms
+            .iter()
+            .find(|(_, room)| room.lock().unwrap().is_open())
+            .map(|(id, room)| (id.clone(), room.clone()));
+        match open {
+            Some(found) => found,
+            None => self.create(),
+        }
+    }
+
+    /// Summary of every live room, suitable for a `rooms` frame.
+    pub fn list(&mut self) -> Vec<RoomSummary> {
+        self.prune();
+        let mut rooms: Vec<RoomSummary> = self
+            .rooms
+            .iter()
+            .map(|(id, room)| {
+                let room = room.lock().unwrap();
+                RoomSummary {
+                    room_id: id.clone(),
+                    players: room.players.len(),
+                    phase: room.phase(),
+                    open: room.is_open(),
+                }
+            })
+            .collect();
+        rooms.sort_by(|a, b| a.room_id.cmp(&b.room_id));
+        rooms
+    }
+
+    /// The room holding the player whose session token is `token`.
+    pub fn find_session(&mut self, token: &str) -> Option<SharedRoom> {
+        self.prune();
+        self.rooms
+            .values()
+            .find(|room| room.lock().unwrap().has_session(token))
+            .cloned()
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if let Some(room) = self.rooms.remove(id) {
+            log::info!("RoomRegistry::remove id={}", id);
+            self.export_log(&room.lock().unwrap());
+            if let Some(store) = &self.store {
+                forget(store, id);
+            }
+        }
+    }
+
+    /// Save every live room to the store, e.g. on shutdown.
+    pub fn persist_all(&self) {
+        for room in self.rooms.values() {
+            room.lock().unwrap().persist();
+        }
+    }
+
+    /// Drop rooms whose game is over, and rooms nobody joined in time;
+    /// the latter have no log worth exporting.
+    fn prune(&mut self) {
+        let mut finished = Vec::new();
+        let mut unused = Vec::new();
+        for (id, room) in &self.rooms {
+            let room = room.lock().unwrap();
+            if room.is_finished() {
+                finished.push(id.clone());
+            } else if room.is_unused(UNUSED_ROOM_TTL) {
+                unused.push(id.clone());
+            }
+        }
+        for id in finished {
+            log::info!("RoomRegistry::prune id={}", id);
+            self.remove(&id);
+        }
+        for id in unused {
+            log::info!("RoomRegistry::prune unused id={}", id);
+            self.rooms.remove(&id);
+            if let Some(store) = &self.store {
+                forget(store, &id);
+            }
+        }
+    }
+
+    /// Write a finished room's event log as JSON lines. Failures are
+    /// logged and otherwise ignored; they must not take the server down.
+    fn export_log(&self, room: &Room) {
+        let Some(dir) = &self.log_dir else {
+            return;
+        };
+        let path = dir.join(format!("{}.jsonl", room.id));
+        let written = std::fs::create_dir_all(dir)
+            .and_then(|()| std::fs::write(&path, room.log().to_json_lines()));
+        match written {
+            Ok(()) => log::info!("RoomRegistry::export_log path={}", path.display()),
+            Err(e) => log::warn!("RoomRegistry::export_log {} failed: {}", path.display(), e),
+        }
+    }
+}
+
+/// Drop a room from the store; failures are logged and otherwise ignored.
+fn forget(store: &SharedStore, id: &str) {
+    if let Err(e) = store.delete(id) {
+        log::warn!("RoomRegistry::forget id={} failed: {}", id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, room::store::FileStore};
+
+    #[test]
+    fn create_returns_distinct_codes() {
+        let mut registry = RoomRegistry::new();
+        let (a, _) = registry.create();
+        let (b, _) = registry.create();
+
+        assert_ne!(a, b);
+        assert_eq!(a.len(), ROOM_CODE_LEN);
+        assert!(registry.get(&a).is_some());
+        assert_eq!(registry.list().len(), 2);
+    }
+
+    #[test]
+    fn find_open_reuses_lobby_room() {
+        let mut registry = RoomRegistry::new();
+        let (a, _) = registry.create();
+        let (b, _) = registry.find_open();
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn finished_room_log_is_exported() {
+        let dir = std::env::temp_dir().join(format!("wss-logs-{}", std::process::id()));
+        let mut registry = RoomRegistry::new().with_log_dir(&dir);
+        let (a, _) = registry.create();
+        registry.remove(&a);
+
+        let text = std::fs::read_to_string(dir.join(format!("{a}.jsonl"))).unwrap();
+        assert!(text.is_empty(), "nothing happened in the room");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stored_rooms_come_back_until_removed() {
+        let dir = std::env::temp_dir().join(format!("wss-rooms-{}", std::process::id()));
+        let store: SharedStore = Arc::new(FileStore::new(&dir));
+        let mut registry = RoomRegistry::new().with_store(store.clone());
+        let (a, room) = registry.create();
+        store.save(&room.lock().unwrap().snapshot()).unwrap();
+
+        let mut rebooted = RoomRegistry::new().with_store(store.clone());
+        assert!(rebooted.get(&a).is_some());
+        rebooted.remove(&a);
+        assert!(store.load_all().unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rooms_nobody_joins_expire() {
+        let clock = Arc::new(ManualClock::new(0));
+        let mut registry = RoomRegistry::new().with_clock(clock.clone());
+        let (a, _) = registry.create();
+        clock.advance(UNUSED_ROOM_TTL.as_millis() as u64 - 1);
+        assert!(registry.get(&a).is_some());
+
+        clock.advance(1);
+        assert!(registry.get(&a).is_none());
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn remove_forgets_room() {
+        let mut registry = RoomRegistry::new();
+        let (a, _) = registry.create();
+        registry.remove(&a);
+
+        assert!(registry.get(&a).is_none());
+        assert!(registry.list().is_empty());
+    }
+}
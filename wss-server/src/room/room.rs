@@ -1,35 +1,122 @@
-use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
 
 use actix::Addr;
-use serde_json::{json, Value};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    types::{Phase, Player, PlayerId, Role, VoteMap},
-    ws::client::{ServerText, WsClient},
+    clock::{Clock, SharedClock, SystemClock},
+    game::{
+        config::{ConfigUpdate, GameConfig, RoleMode, ShufflePolicy, TieRule, VoteRule},
+        role::assign_roles,
+    },
+    message::{
+        self, AckStatus, Ballot, CardRef, CardTaken, ChatChannel, ChatLine, DayEnd,
+        DecryptProgress, DecryptRequest, ErrorCode, FlaggedDecryption, FullView, GameOver,
+        LobbyConfig, LobbyPlayer, LobbyState, NightEnd, PartialDecryption, PeekResult, PhaseChange,
+        PlayerInfo, PlayerRef, PlayerRole, PlayerStatus, Reason, RoleCard, RunoffVote, ServerEvent,
+        ShuffleFault, ShuffleTurn, ShuffledDeck, Snapshot, SpectatorState, TakenCards, VoteCount,
+        VoteTally,
+    },
+    prover::{CardClaim, DecryptionClaim, KeyClaim, ShuffleClaim},
+    room::{
+        log::{DeathCause, EventLog, GameEvent},
+        store::{RoomSnapshot, SeatSnapshot, SharedStore},
+    },
+    types::{Phase, Player, PlayerId, Role, RoomId, Spectator, VoteMap},
+    ws::client::{ServerText, Unseated, WsClient},
 };
 
 pub type SharedRoom = Arc<Mutex<Room>>;
 
 pub struct Room {
+    pub id: RoomId,
+    /// Handle back to the shared room, used by timers to re-enter it.
+    self_ref: Weak<Mutex<Room>>,
+    pub config: GameConfig,
+    pub host: Option<PlayerId>,
     pub players: HashMap<PlayerId, Player>,
+    /// Read-only connections, keyed by their connection id.
+    spectators: HashMap<String, Spectator>,
+    /// Player IDs in the order they joined. Every ordering the players must
+    /// agree on (key aggregation, shuffle turns, decryption helpers, vote
+    /// tallies) follows it.
+    pub seats: Vec<PlayerId>,
     phase: Phase,
     round: u32,
+    /// Bumped on every phase change so stale timers can tell they expired.
+    phase_seq: u64,
+    /// Unix milliseconds at which the current phase times out.
+    phase_deadline: Option<u64>,
     game_started: bool,
+    /// Every player left for good; the registry may drop the room.
+    abandoned: bool,
     pending_night: HashMap<PlayerId, (String, String)>,
     votes: VoteMap,
+    /// The tied players, while a runoff vote between them is on.
+    runoff: Option<Vec<PlayerId>>,
+    /// Keys whose owners have proven they hold the secret key.
     pub public_keys: HashMap<PlayerId, String>,
+    /// Keys waiting for a signature over the nonce handed out with them.
+    key_challenges: HashMap<PlayerId, KeyClaim>,
     pub shuffle_order: Vec<PlayerId>,
     pub shuffle_index: usize,
+    /// Bumped on every shuffle turn so a stale turn timer can tell.
+    shuffle_seq: u64,
+    /// Invalid proofs sent in the current shuffle turn.
+    shuffle_attempts: u32,
+    /// The deck as it was before anyone shuffled, for restarts.
+    original_deck: Vec<[String; 2]>,
     pub agg_pk: String,
+    pub aggregation: Aggregation,
     pub deck_state: Vec<[String; 2]>,
+    /// The shuffle is done and cards may be picked.
+    picking: bool,
     pub taken_cards: HashMap<PlayerId, usize>,
     pub decrypt_ctx: HashMap<PlayerId, DecryptCtx>,
-    pub night_wolf_target: Option<String>,
-    pub night_seer_target: Option<String>,
+    /// Helpers caught relaying a partial decryption with a bad proof.
+    pub flagged_helpers: HashSet<PlayerId>,
     pub night_done: bool,
+    /// The winning side. In mental poker it is held back while cards are
+    /// revealed, and the proven cards may still overturn it.
+    winner: Option<&'static str>,
+    /// Roles proven with `revealCard` at the end of the game.
+    reveals: HashMap<PlayerId, Role>,
+    /// Every state transition so far, for export and replay.
+    log: EventLog,
+    /// Where the room saves itself to survive a restart, if anywhere.
+    store: Option<SharedStore>,
+    /// Stamps deadlines and log entries.
+    clock: SharedClock,
+    /// Unix milliseconds at which the room was created or restored.
+    created_at: u64,
 }
 
+/// How a day vote ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DayOutcome {
+    Lynch(PlayerId),
+    NoLynch,
+    /// Tied players go to a second vote.
+    Runoff(Vec<PlayerId>),
+}
+
+/// Progress of the public-key aggregation that opens mental-poker dealing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Aggregation {
+    Idle,
+    /// A prover call is in flight, outside the room lock.
+    Running,
+    /// The last attempt failed; waits for a `retryAggregation`.
+    Failed(String),
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DecryptCtx {
     pub helpers: VecDeque<PlayerId>, // queue B → C → D
     pub current_cipher: [String; 2], // starts with chosen card
@@ -38,50 +125,221 @@ pub struct DecryptCtx {
 }
 
 impl Room {
-    pub fn new() -> Self {
-        println!("Room::new");
+    pub fn new(id: RoomId) -> Self {
+        log::info!("Room::new id={}", id);
+        let config = GameConfig::default();
         Self {
+            id,
+            self_ref: Weak::new(),
+            config,
+            host: None,
             players: HashMap::new(),
+            spectators: HashMap::new(),
+            seats: Vec::new(),
             phase: Phase::Lobby,
             round: 0,
+            phase_seq: 0,
+            phase_deadline: None,
             game_started: false,
+            abandoned: false,
             pending_night: HashMap::new(),
             votes: HashMap::new(),
+            runoff: None,
             public_keys: HashMap::new(),
+            key_challenges: HashMap::new(),
             shuffle_order: Vec::new(),
             shuffle_index: 0,
+            shuffle_seq: 0,
+            shuffle_attempts: 0,
+            original_deck: Vec::new(),
             agg_pk: String::new(),
-            deck_state: vec![
-                ["1".into(), "10".into()], // Wolf
-                ["1".into(), "1".into()],  // Seer
-                ["1".into(), "2".into()],  // Villager
-                ["1".into(), "2".into()],  // Villager
-            ],
+            aggregation: Aggregation::Idle,
+            deck_state: config.roles.deck(),
+            picking: false,
             taken_cards: HashMap::new(),
             decrypt_ctx: HashMap::new(),
-            night_wolf_target: None,
-            night_seer_target: None,
+            flagged_helpers: HashSet::new(),
             night_done: false,
+            winner: None,
+            reveals: HashMap::new(),
+            log: EventLog::new(),
+            store: None,
+            clock: Arc::new(SystemClock),
+            created_at: SystemClock.now_millis(),
+        }
+    }
+    /// A room starting out with `config` and telling time by `clock`, whose
+    /// phase timers can reach back into it.
+    pub fn new_shared(id: RoomId, config: GameConfig, clock: SharedClock) -> SharedRoom {
+        Arc::new_cyclic(|weak| {
+            let mut room = Room::new(id);
+            room.config = config;
+            room.created_at = clock.now_millis();
+            room.clock = clock;
+            room.deck_state = config.roles.deck();
+            room.self_ref = weak.clone();
+            Mutex::new(room)
+        })
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Still in the lobby with a free seat.
+    pub fn is_open(&self) -> bool {
+        !self.game_started
+            && self.phase == Phase::Lobby
+            && self.players.len() < self.config.player_count()
+    }
+
+    /// Host-only: change the role composition (and so the seat count), the
+    /// role mode, the shuffle fault policy or the vote rules before the game
+    /// starts.
+    pub fn configure(&mut self, id: &PlayerId, update: ConfigUpdate) -> Result<(), String> {
+        log::info!("Room::configure id={} update={:?}", id, update);
+        if self.host.as_ref() != Some(id) {
+            return Err("only the host can configure the game".into());
+        }
+        if self.game_started || self.phase != Phase::Lobby {
+            return Err("the game has already started".into());
+        }
+        let config = self.config.updated(update);
+        config.validate()?;
+        if config.player_count() < self.players.len() {
+            return Err(format!(
+                "{} players already seated, cannot shrink to {}",
+                self.players.len(),
+                config.player_count()
+            ));
+        }
+        self.config = config;
+        self.deck_state = config.roles.deck();
+        self.record(GameEvent::Configured { config });
+        self.persist();
+        self.broadcast_lobby();
+        self.try_start();
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.phase == Phase::GameOver || self.abandoned
+    }
+
+    /// Nobody has taken a seat in the `ttl` since the room was created.
+    pub fn is_unused(&self, ttl: Duration) -> bool {
+        self.players.is_empty()
+            && self.log.entries().is_empty()
+            && self.clock.now_millis() >= self.created_at + ttl.as_millis() as u64
+    }
+
+    /// Players in seat order.
+    pub fn seated(&self) -> impl Iterator<Item = &Player> {
+        self.seats.iter().filter_map(|id| self.players.get(id))
+    }
+
+    /// Registered public keys in seat order, as the aggregation circuit
+    /// gets them.
+    pub fn ordered_public_keys(&self) -> Vec<String> {
+        self.seats
+            .iter()
+            .filter_map(|pid| self.public_keys.get(pid))
+            .cloned()
+            .collect()
+    }
+
+    /// Start registering `pk` as `id`'s key: the returned claim carries a
+    /// fresh nonce the player must sign with the matching secret key. Keys
+    /// are fixed once the game starts, and no two seats share one.
+    pub fn key_challenge(&mut self, id: &PlayerId, pk: String) -> Result<KeyClaim, String> {
+        if !self.players.contains_key(id) {
+            return Err("unknown player".into());
+        }
+        if self.game_started {
+            return Err("keys cannot change once the game has started".into());
         }
+        if self
+            .public_keys
+            .iter()
+            .any(|(owner, key)| owner != id && *key == pk)
+        {
+            return Err("that key is registered to another player".into());
+        }
+        let claim = KeyClaim {
+            public_key: pk,
+            nonce: rand::random::<u128>().to_string(),
+        };
+        log::info!(
+            "Room::key_challenge id={} pk={} nonce={}",
+            id,
+            claim.public_key,
+            claim.nonce
+        );
+        self.key_challenges.insert(id.clone(), claim.clone());
+        Ok(claim)
     }
-    pub fn night_action_verified(&mut self, player: PlayerId, action: String, target: String) {
-        match action.as_str() {
-            "wolfKill" => self.night_wolf_target = Some(target),
-            "seerPeek" => self.night_seer_target = Some(target),
-            _ => { /* villager or non-action → ignore */ }
+
+    /// `id`'s signature over `claim` verified: the key is theirs. Replaces
+    /// a key they registered earlier in the lobby.
+    pub fn accept_key(&mut self, id: &PlayerId, claim: KeyClaim) -> Result<(), String> {
+        if self.game_started {
+            return Err("keys cannot change once the game has started".into());
         }
+        if self.key_challenges.get(id) != Some(&claim) {
+            return Err("the challenge was replaced while your signature was checked".into());
+        }
+        log::info!("Room::accept_key id={} pk={}", id, claim.public_key);
+        self.key_challenges.remove(id);
+        self.record(GameEvent::KeyRegistered {
+            player_id: id.clone(),
+            public_key: claim.public_key.clone(),
+        });
+        self.public_keys.insert(id.clone(), claim.public_key);
+        self.persist();
+        Ok(())
     }
-    pub fn night_ready(&self) -> bool {
-        self.night_wolf_target.is_some() && self.night_seer_target.is_some()
+
+    /// The outstanding challenge `id` has to answer, if any.
+    pub fn key_claim(&self, id: &PlayerId) -> Result<KeyClaim, String> {
+        self.key_challenges
+            .get(id)
+            .cloned()
+            .ok_or_else(|| "send registerPublicKey first".into())
     }
-    pub fn register_public_key(&mut self, player_id: &PlayerId, pk: String) {
-        // you can log here to debug:
-        println!("Room::register_public_key id={} pk={}", player_id, pk);
-        self.public_keys.insert(player_id.clone(), pk);
+
+    /// `id`'s signature did not verify; they need a fresh nonce to retry.
+    pub fn reject_key(&mut self, id: &PlayerId, claim: &KeyClaim) {
+        log::warn!("Room::reject_key id={} pk={}", id, claim.public_key);
+        if self.key_challenges.get(id) == Some(claim) {
+            self.key_challenges.remove(id);
+        }
     }
 
-    pub fn add_player(&mut self, id: PlayerId, name: String, addr: Addr<WsClient>) {
-        println!("Room::add_player id={} name={} addr added", id, name);
+    /// Seat a new player and return the session token they can later
+    /// `resume` with.
+    pub fn add_player(
+        &mut self,
+        id: PlayerId,
+        name: String,
+        addr: Addr<WsClient>,
+    ) -> Result<String, String> {
+        log::info!("Room::add_player id={} name={} addr added", id, name);
+        if !self.is_open() {
+            return Err(format!("room {} is not accepting players", self.id));
+        }
+        if self.host.is_none() {
+            self.host = Some(id.clone());
+        }
+        let session_token = Uuid::new_v4().to_string();
+        self.record(GameEvent::PlayerJoined {
+            player_id: id.clone(),
+            name: name.clone(),
+        });
+        self.seats.push(id.clone());
         self.players.insert(
             id.clone(),
             Player {
@@ -91,369 +349,2792 @@ impl Room {
                 is_ready: false,
                 is_alive: true,
                 addr: Some(addr),
+                session_token: session_token.clone(),
+                connection_epoch: 0,
             },
         );
+        self.persist();
         self.broadcast_lobby();
+        Ok(session_token)
     }
 
-    fn broadcast_lobby(&self) {
-        println!("Room::broadcast_lobby players={}", self.players.len());
-        let snapshot = json!({
-            "type": 1,
-            "target": "lobby",
-            "arguments": [{
-                "players": self.players.values().map(|p| json!({
-                    "id": p.id,
-                    "name": p.name,
-                    "ready": p.is_ready
-                })).collect::<Vec<_>>()
-            }]
-        })
-        .to_string();
-        for player in self.players.values() {
-            if let Some(addr) = &player.addr {
-                addr.do_send(ServerText(snapshot.clone()));
-            }
+    /// The player's socket closed. They keep their seat for the grace
+    /// period; `addr` identifies the connection that closed so a late close
+    /// of a replaced socket does not unbind the resumed one.
+    pub fn disconnect(&mut self, id: &PlayerId, addr: &Addr<WsClient>) {
+        let Some(player) = self.players.get_mut(id) else {
+            return;
+        };
+        if player.addr.as_ref() != Some(addr) {
+            return;
         }
-    }
+        log::info!("Room::disconnect id={}", id);
+        player.addr = None;
+        player.connection_epoch += 1;
+        let epoch = player.connection_epoch;
 
-    pub fn set_ready(&mut self, id: PlayerId, ready: bool) {
-        println!("Room::set_ready id={} ready={}", id, ready);
-        if let Some(player) = self.players.get_mut(&id) {
-            player.is_ready = ready;
-            self.broadcast_lobby();
+        if self.game_started {
+            self.broadcast(&ServerEvent::PlayerDisconnected(PlayerRef {
+                player_id: id.clone(),
+            }));
         } else {
-            println!("Room::set_ready unknown id={}", id);
+            self.broadcast_lobby();
         }
-        self.try_start()
-    }
 
-    pub fn try_start(&mut self) {
-        let ready_count = self.players.values().filter(|p| p.is_ready).count();
-        let key_count = self.public_keys.len();
-        let expected = 4;
-        println!(
-            "Room::try_start game_started={} ready_count={} key_count={}",
-            self.game_started, ready_count, key_count
+        let id = id.clone();
+        self.schedule(
+            Duration::from_secs(self.config.reconnect_grace),
+            move |room| room.on_grace_expired(&id, epoch),
         );
-        if self.game_started {
-            return;
-        }
-        if !self.game_started && ready_count == expected && key_count == expected {
-            self.game_started = true;
-            self.start_game();
-        }
     }
 
-    fn start_game(&mut self) {
-        println!("Room::start_game");
-        let players_info: Vec<_> = self
+    /// Rebind a new connection to the player holding `token` and send them
+    /// a full snapshot of the game. Returns the player's ID.
+    pub fn resume(&mut self, token: &str, addr: Addr<WsClient>) -> Result<PlayerId, String> {
+        let player = self
             .players
-            .values()
-            .map(|p| {
-                json!({
-                    "id": p.id,
-                    "name": p.name
-                })
-            })
-            .collect();
-        let start_frame = json!({
-            "type":1,
-            "target":"gameStart",
-            "arguments":[{"players":players_info}]
-        })
-        .to_string();
-        for p in self.players.values() {
-            if let Some(addr) = &p.addr {
-                addr.do_send(crate::ws::client::ServerText(start_frame.clone()));
-            }
-        }
-        self.phase = Phase::Night;
-        self.round = 1;
-        let phase_frame = json!({
-            "type":1,
-            "target":"phase",
-            "arguments":[{"phase":"night","round":self.round,"duration":30}]
-        })
-        .to_string();
-        for p in self.players.values() {
-            if let Some(addr) = &p.addr {
-                addr.do_send(crate::ws::client::ServerText(phase_frame.clone()));
-            }
+            .values_mut()
+            .find(|p| p.session_token == token)
+            .ok_or("unknown session token")?;
+        log::info!("Room::resume id={}", player.id);
+        player.addr = Some(addr);
+        player.connection_epoch += 1;
+        let id = player.id.clone();
+
+        self.send_to(&id, &ServerEvent::Resumed(self.snapshot_for(&id)));
+        if self.game_started {
+            self.broadcast(&ServerEvent::PlayerReconnected(PlayerRef {
+                player_id: id.clone(),
+            }));
+            self.resend_pending_requests(&id);
+        } else {
+            self.broadcast_lobby();
         }
+        Ok(id)
     }
 
-    pub fn night_action(&mut self, id: PlayerId, action: String, target: String) {
-        println!(
-            "Room::night_action id={} action={} target={}",
-            id, action, target
-        );
-        if self.phase != Phase::Night {
-            return;
-        }
-        self.pending_night.insert(id.clone(), (action, target));
-        if self.pending_night.len() == self.required_night_actions() {
-            self.resolve_night();
+    pub fn has_session(&self, token: &str) -> bool {
+        self.players.values().any(|p| p.session_token == token)
+    }
+
+    /// Everything a resuming client needs to rebuild its view.
+    fn snapshot_for(&self, id: &PlayerId) -> Snapshot {
+        let me = &self.players[id];
+        Snapshot {
+            room_id: self.id.clone(),
+            player_id: id.clone(),
+            session_token: me.session_token.clone(),
+            phase: self.phase,
+            round: self.round,
+            deadline: self.phase_deadline,
+            players: self.player_statuses(),
+            alive: me.is_alive,
+            role: me.role,
+            taken_card: self.taken_cards.get(id).copied(),
+            decrypt: self.decrypt_ctx.get(id).map(|ctx| DecryptProgress {
+                card: ctx.card_index,
+                components: ctx.components.clone(),
+                pending_helpers: ctx.helpers.iter().cloned().collect(),
+            }),
         }
     }
 
-    fn required_night_actions(&self) -> usize {
-        let count = self
-            .players
-            .values()
-            .filter(|p| p.is_alive)
-            .filter(|p| matches!(p.role, Some(Role::Werewolf) | Some(Role::Seer)))
-            .count();
-        println!("Room::required_night_actions count={}", count);
-        count
+    fn player_statuses(&self) -> Vec<PlayerStatus> {
+        self.seated()
+            .map(|p| PlayerStatus {
+                id: p.id.clone(),
+                name: p.name.clone(),
+                alive: p.is_alive,
+                connected: p.addr.is_some(),
+            })
+            .collect()
     }
 
-    pub fn resolve_night(&mut self) {
-        println!("Room::resolve_night pending={}", self.pending_night.len());
-        if self.night_done {
-            return;
+    /// Re-send the requests the player may have missed while away: their
+    /// shuffle turn and any partial decryptions waiting on them.
+    fn resend_pending_requests(&self, id: &PlayerId) {
+        if let Aggregation::Failed(reason) = &self.aggregation {
+            self.send_to(
+                id,
+                &ServerEvent::AggregationFailed(Reason {
+                    reason: reason.clone(),
+                }),
+            );
         }
-        self.night_done = true;
-        let mut killed: Option<PlayerId> = None;
-        for (action, target) in self.pending_night.values() {
-            if action == "kill" {
-                killed = Some(target.clone());
-            }
+        if self.shuffle_order.get(self.shuffle_index) == Some(id) {
+            self.send_to(id, &self.shuffle_turn());
         }
-        if let Some(ref id) = killed {
-            println!("Room::resolve_night killed={}", id);
-            if let Some(victim) = self.players.get_mut(id) {
-                victim.is_alive = false;
-            }
+        if self.picking {
+            self.send_to(
+                id,
+                &ServerEvent::ShuffleComplete(ShuffledDeck {
+                    deck: self.deck_state.clone(),
+                }),
+            );
+            self.send_to(id, &self.cards_taken());
         }
-        for (actor, (action, target)) in &self.pending_night {
-            if action == "peek" {
-                println!("Room::resolve_night peek actor={} target={}", actor, target);
-                if let Some(seer) = self.players.get(actor) {
-                    if let Some(target_player) = self.players.get(target) {
-                        let peek_frame = json!({
-                            "type":1,
-                            "target":"peekResult",
-                            "arguments":[{"target":target,"role":format!("{:?}",target_player.role.unwrap())}]
-                        })
-                        .to_string();
-                        if let Some(addr) = &seer.addr {
-                            addr.do_send(crate::ws::client::ServerText(peek_frame));
-                        }
-                    }
-                }
+        for (requester, ctx) in &self.decrypt_ctx {
+            if ctx.helpers.front() == Some(id) {
+                self.send_need_decrypt(requester);
             }
         }
-        let night_end = json!({
-            "type":1,
-            "target":"nightEnd",
-            "arguments":[{"killed":killed}]
-        })
-        .to_string();
-        println!("Room::resolve_night broadcast nightEnd");
-        for p in self.players.values() {
-            if let Some(addr) = &p.addr {
-                addr.do_send(crate::ws::client::ServerText(night_end.clone()));
+    }
+
+    /// The grace period after disconnect `epoch` ran out. If the player is
+    /// still away they leave the lobby, die if the game is running, or,
+    /// while dealing, are dropped and the room goes back to the lobby:
+    /// dealing cannot finish without their key and decryption layers.
+    fn on_grace_expired(&mut self, id: &PlayerId, epoch: u64) {
+        let still_gone = self
+            .players
+            .get(id)
+            .is_some_and(|p| p.addr.is_none() && p.connection_epoch == epoch);
+        if !still_gone {
+            return;
+        }
+        log::info!("Room::on_grace_expired id={}", id);
+
+        if !self.game_started {
+            self.remove_from_lobby(id);
+            if self.players.is_empty() {
+                self.abandoned = true;
+                return;
             }
+            self.broadcast_lobby();
+            return;
         }
-        if let Some(winner) = self.check_win() {
-            println!("Room::resolve_night win={}", winner);
-            self.broadcast_game_over(winner);
-            self.phase = Phase::GameOver;
+        if self.phase == Phase::Dealing {
+            self.kick_from_dealing(id);
             return;
         }
-        self.pending_night.clear();
-        self.phase = Phase::Day;
-        let day_frame = json!({
-            "type":1,
-            "target":"phase",
-            "arguments":[{"phase":"day","round":self.round,"duration":60}]
-        })
-        .to_string();
-        println!("Room::resolve_night broadcast phase=day");
-        for p in self.players.values() {
-            if let Some(addr) = &p.addr {
-                addr.do_send(crate::ws::client::ServerText(day_frame.clone()));
+
+        self.kill(id, DeathCause::Left);
+        self.persist();
+        self.broadcast(&ServerEvent::PlayerLeft(PlayerRef {
+            player_id: id.clone(),
+        }));
+
+        if matches!(self.phase, Phase::Night | Phase::Day) {
+            if let Some(winner) = self.check_win() {
+                self.end_game(winner);
+                return;
             }
         }
+        if self.phase == Phase::Day {
+            self.drop_ballots(id);
+        }
+        if self.players.values().all(|p| p.addr.is_none()) {
+            self.abandoned = true;
+        }
     }
 
-    fn living_count(&self) -> usize {
-        let count = self.players.values().filter(|p| p.is_alive).count();
-        println!("Room::living_count ={}", count);
-        count
+    /// Free `id`'s seat; the host passes to the next player in seat order.
+    fn remove_from_lobby(&mut self, id: &PlayerId) {
+        self.record(GameEvent::PlayerRemoved {
+            player_id: id.clone(),
+        });
+        self.players.remove(id);
+        self.seats.retain(|seat| seat != id);
+        self.public_keys.remove(id);
+        self.key_challenges.remove(id);
+        if self.host.as_ref() == Some(id) {
+            self.host = self.seats.first().cloned();
+        }
+        self.persist();
     }
 
-    pub fn vote(&mut self, voter: PlayerId, target: PlayerId) {
-        println!("Room::vote voter={} target={}", voter, target);
-        if self.phase != Phase::Day {
-            return;
+    fn kill(&mut self, id: &PlayerId, cause: DeathCause) {
+        self.record(GameEvent::Died {
+            player_id: id.clone(),
+            cause,
+        });
+        if let Some(player) = self.players.get_mut(id) {
+            player.is_alive = false;
         }
-        if !self.players.get(&voter).map_or(false, |p| p.is_alive) {
+    }
+
+    fn record(&mut self, event: GameEvent) {
+        self.log.record(self.clock.now_millis(), event);
+    }
+
+    pub fn log(&self) -> &EventLog {
+        &self.log
+    }
+
+    /// Save rooms to `store` from now on.
+    pub fn set_store(&mut self, store: SharedStore) {
+        self.store = Some(store);
+    }
+
+    /// Save the room to its store, if it has one. A failed save is logged
+    /// and the game goes on.
+    pub fn persist(&self) {
+        let Some(store) = &self.store else {
             return;
+        };
+        if let Err(e) = store.save(&self.snapshot()) {
+            log::warn!("Room::persist id={} failed: {}", self.id, e);
         }
-        self.votes.insert(voter.clone(), target.clone());
-        let tally_frame =
-            json!({"type":1,"target":"voteUpdate","arguments":[self.votes]}).to_string();
-        println!("Room::vote broadcast voteUpdate");
-        for p in self.players.values() {
-            if let Some(addr) = &p.addr {
-                addr.do_send(ServerText(tally_frame.clone()));
-            }
-        }
-        if self.votes.len() == self.living_count() {
-            self.resolve_day();
+    }
+
+    /// The room's state without its connections and timers.
+    pub fn snapshot(&self) -> RoomSnapshot {
+        RoomSnapshot {
+            id: self.id.clone(),
+            config: self.config,
+            host: self.host.clone(),
+            seats: self
+                .seated()
+                .map(|p| SeatSnapshot {
+                    id: p.id.clone(),
+                    name: p.name.clone(),
+                    role: p.role,
+                    ready: p.is_ready,
+                    alive: p.is_alive,
+                    session_token: p.session_token.clone(),
+                })
+                .collect(),
+            phase: self.phase,
+            round: self.round,
+            deadline: self.phase_deadline,
+            game_started: self.game_started,
+            pending_night: self.pending_night.clone(),
+            votes: self.votes.clone(),
+            runoff: self.runoff.clone(),
+            public_keys: self.public_keys.clone(),
+            shuffle_order: self.shuffle_order.clone(),
+            shuffle_index: self.shuffle_index,
+            original_deck: self.original_deck.clone(),
+            agg_pk: self.agg_pk.clone(),
+            aggregation: self.aggregation.clone(),
+            deck: self.deck_state.clone(),
+            picking: self.picking,
+            taken_cards: self.taken_cards.clone(),
+            decrypt: self.decrypt_ctx.clone(),
+            flagged_helpers: self.flagged_helpers.clone(),
+            night_done: self.night_done,
+            winner: self.winner.map(str::to_string),
+            reveals: self.reveals.clone(),
+            log: self.log.clone(),
         }
     }
 
-    fn resolve_day(&mut self) {
-        println!(
-            "Room::resolve_day votes={} pending_night={} players_alive={}",
-            self.votes.len(),
-            self.pending_night.len(),
-            self.living_count()
+    /// Bring back a room saved before a restart. Nobody is connected yet,
+    /// so every player's reconnect grace period starts now; the phase keeps
+    /// its original deadline.
+    pub fn restore(
+        snapshot: RoomSnapshot,
+        clock: SharedClock,
+        store: Option<SharedStore>,
+    ) -> SharedRoom {
+        log::info!(
+            "Room::restore id={} phase={:?} round={}",
+            snapshot.id,
+            snapshot.phase,
+            snapshot.round
         );
-        let mut counts: HashMap<&PlayerId, usize> = HashMap::new();
-        for tgt in self.votes.values() {
-            *counts.entry(tgt).or_default() += 1;
+        let shared = Room::new_shared(snapshot.id.clone(), snapshot.config, clock);
+        {
+            let mut room = shared.lock().unwrap();
+            room.load(snapshot);
+            room.store = store;
+            room.resume_timers();
         }
-        let (lynched, _max) = counts
-            .iter()
-            .max_by_key(|(_, c)| *c)
-            .map(|(id, c)| ((*id).clone(), *c))
-            .unwrap_or((String::new(), 0));
-        let lynch_opt = if _max > 0 && counts.values().filter(|&&c| c == _max).count() == 1 {
-            Some(lynched.clone())
-        } else {
-            None
-        };
-        if let Some(id) = &lynch_opt {
-            println!("Room::resolve_day lynched={}", id);
-            if let Some(p) = self.players.get_mut(id) {
-                p.is_alive = false;
-            }
-        } else {
-            println!("Room::resolve_day lynch skip");
+        shared
+    }
+
+    fn load(&mut self, snapshot: RoomSnapshot) {
+        self.config = snapshot.config;
+        self.host = snapshot.host;
+        self.seats = snapshot.seats.iter().map(|seat| seat.id.clone()).collect();
+        self.players = snapshot
+            .seats
+            .into_iter()
+            .map(|seat| {
+                let player = Player {
+                    id: seat.id.clone(),
+                    name: seat.name,
+                    role: seat.role,
+                    is_ready: seat.ready,
+                    is_alive: seat.alive,
+                    addr: None,
+                    session_token: seat.session_token,
+                    connection_epoch: 0,
+                };
+                (seat.id, player)
+            })
+            .collect();
+        self.phase = snapshot.phase;
+        self.round = snapshot.round;
+        self.phase_deadline = snapshot.deadline;
+        self.game_started = snapshot.game_started;
+        self.pending_night = snapshot.pending_night;
+        self.votes = snapshot.votes;
+        self.runoff = snapshot.runoff;
+        self.public_keys = snapshot.public_keys;
+        self.shuffle_order = snapshot.shuffle_order;
+        self.shuffle_index = snapshot.shuffle_index;
+        self.original_deck = snapshot.original_deck;
+        self.agg_pk = snapshot.agg_pk;
+        self.aggregation = snapshot.aggregation;
+        self.deck_state = snapshot.deck;
+        self.picking = snapshot.picking;
+        self.taken_cards = snapshot.taken_cards;
+        self.decrypt_ctx = snapshot.decrypt;
+        self.flagged_helpers = snapshot.flagged_helpers;
+        self.night_done = snapshot.night_done;
+        self.winner = snapshot.winner.as_deref().and_then(side);
+        self.reveals = snapshot.reveals;
+        self.log = snapshot.log;
+    }
+
+    /// Re-arm what the restart cut short: grace timers for the absent
+    /// players, the phase timer, the current shuffle turn. An aggregation
+    /// that was in flight is lost and has to be retried.
+    fn resume_timers(&mut self) {
+        if self.aggregation == Aggregation::Running {
+            self.aggregation = Aggregation::Failed("interrupted by a server restart".into());
         }
-        let frame =
-            json!({"type":1,"target":"dayEnd","arguments":[{"lynched":lynch_opt}]}).to_string();
-        println!("Room::resolve_day broadcast dayEnd");
-        for p in self.players.values() {
-            if let Some(addr) = &p.addr {
-                addr.do_send(ServerText(frame.clone()));
-            }
+        let grace = Duration::from_secs(self.config.reconnect_grace);
+        for id in self.seats.clone() {
+            self.schedule(grace, move |room| room.on_grace_expired(&id, 0));
         }
-        if let Some(winner) = self.check_win() {
-            println!("Room::resolve_day win={}", winner);
-            self.broadcast_game_over(winner);
-            self.phase = Phase::GameOver;
-            return;
+        if let Some(deadline) = self.phase_deadline {
+            self.schedule_phase_timeout(Duration::from_millis(
+                deadline.saturating_sub(self.clock.now_millis()),
+            ));
         }
-        self.votes.clear();
-        self.pending_night.clear();
-        self.phase = Phase::Night;
-        self.round += 1;
-        let night_frame = json!({"type":1,"target":"phase","arguments":[{"phase":"night","round":self.round,"duration":30}]}).to_string();
-        println!("Room::resolve_day broadcast phase=night");
-        for p in self.players.values() {
-            if let Some(addr) = &p.addr {
-                addr.do_send(ServerText(night_frame.clone()));
+        if self.phase == Phase::Dealing
+            && self.aggregation == Aggregation::Done
+            && self.shuffle_index < self.shuffle_order.len()
+        {
+            self.start_shuffle_turn();
+        }
+    }
+
+    /// Rebuild a room's state from its event log, for looking into a
+    /// disputed game. The replayed room has no connections, runs no timers
+    /// and verifies nothing: it takes the log's word for every step.
+    pub fn replay(id: RoomId, log: &EventLog) -> Room {
+        let mut room = Room::new(id);
+        for entry in log.entries() {
+            room.apply(&entry.event);
+        }
+        room.log = log.clone();
+        room
+    }
+
+    fn apply(&mut self, event: &GameEvent) {
+        match event.clone() {
+            GameEvent::PlayerJoined { player_id, name } => {
+                if self.host.is_none() {
+                    self.host = Some(player_id.clone());
+                }
+                self.seats.push(player_id.clone());
+                self.players.insert(
+                    player_id.clone(),
+                    Player {
+                        id: player_id,
+                        name,
+                        role: None,
+                        is_ready: false,
+                        is_alive: true,
+                        addr: None,
+                        session_token: String::new(),
+                        connection_epoch: 0,
+                    },
+                );
+            }
+            GameEvent::PlayerRemoved { player_id } if self.game_started => {
+                self.kick_from_dealing(&player_id)
+            }
+            GameEvent::PlayerRemoved { player_id } => self.remove_from_lobby(&player_id),
+            GameEvent::Ready { player_id, ready } => {
+                if let Some(player) = self.players.get_mut(&player_id) {
+                    player.is_ready = ready;
+                }
+            }
+            GameEvent::Configured { config } => {
+                self.config = config;
+                self.deck_state = config.roles.deck();
+            }
+            GameEvent::KeyRegistered {
+                player_id,
+                public_key,
+            } => {
+                self.public_keys.insert(player_id, public_key);
+            }
+            GameEvent::GameStarted { seats } => {
+                self.game_started = true;
+                self.seats = seats;
+                if self.config.role_mode == RoleMode::MentalPoker {
+                    self.phase = Phase::Dealing;
+                }
+            }
+            GameEvent::RolesDealt { roles } => {
+                for (id, role) in roles {
+                    if let Some(player) = self.players.get_mut(&id) {
+                        player.role = Some(role);
+                    }
+                }
+            }
+            GameEvent::KeysAggregated { agg_pk } => {
+                self.agg_pk = agg_pk;
+                self.aggregation = Aggregation::Done;
+                self.original_deck = self.deck_state.clone();
+            }
+            GameEvent::ShuffleAccepted { deck, .. } => self.deck_state = deck,
+            GameEvent::ShuffleFault { policy, .. } => {
+                if policy == ShufflePolicy::Restart {
+                    self.deck_state = self.original_deck.clone();
+                }
+            }
+            GameEvent::CardPicked { player_id, card } => {
+                self.taken_cards.insert(player_id, card);
+            }
+            GameEvent::RoleRevealed { player_id, role } => {
+                if let Some(player) = self.players.get_mut(&player_id) {
+                    player.role = Some(role);
+                }
+            }
+            GameEvent::PhaseStarted { phase, round } => {
+                self.phase = phase;
+                self.round = round;
+                self.votes.clear();
+                self.pending_night.clear();
+            }
+            GameEvent::NightAction {
+                player_id,
+                action,
+                target,
+            } => {
+                self.pending_night.insert(player_id, (action, target));
+            }
+            GameEvent::Vote { voter, target } => {
+                self.votes.insert(voter, target);
+            }
+            GameEvent::VoteRetracted { voter } => {
+                self.votes.remove(&voter);
+            }
+            GameEvent::Died { player_id, .. } => {
+                if let Some(player) = self.players.get_mut(&player_id) {
+                    player.is_alive = false;
+                }
+            }
+            GameEvent::CardRevealed { player_id, role } => {
+                self.reveals.insert(player_id, role);
+            }
+            GameEvent::GameOver { winner, .. } => {
+                self.winner = side(&winner);
+                self.phase = Phase::GameOver;
             }
         }
     }
 
-    fn check_win(&self) -> Option<&'static str> {
-        let wolf_alive = self
-            .players
-            .values()
-            .any(|p| p.is_alive && p.role == Some(Role::Werewolf));
-        let villagers_alive = self
-            .players
-            .values()
-            .any(|p| p.is_alive && p.role != Some(Role::Werewolf));
-        let result = match (wolf_alive, villagers_alive) {
-            (false, true) => Some("villagers"),
-            (true, false) => Some("werewolves"),
-            _ => None,
-        };
-        println!("Room::check_win -> {:?}", result);
-        result
+    /// What a replay shows: the winner, if the game got that far.
+    pub fn winner(&self) -> Option<&'static str> {
+        self.winner
     }
 
-    fn broadcast_game_over(&self, winner: &str) {
-        println!("Room::broadcast_game_over winner={}", winner);
-        let role_map = self
-            .players
-            .iter()
-            .map(|(id, p)| (id.clone(), Value::String(format!("{:?}", p.role.unwrap()))))
-            .collect::<serde_json::Map<_, _>>();
-        let frame =
-            json!({"type":1,"target":"gameOver","arguments":[{"winner":winner,"roles":role_map}]})
-                .to_string();
-        println!("Room::broadcast_game_over frame={}", frame);
+    /// Whose ballot is against whom (`None`: abstained).
+    pub fn votes(&self) -> &VoteMap {
+        &self.votes
+    }
+
+    pub fn send_to(&self, id: &PlayerId, event: &ServerEvent) {
+        if let Some(addr) = self.players.get(id).and_then(|p| p.addr.as_ref()) {
+            addr.do_send(ServerText(event.to_frame()));
+        }
+    }
+
+    /// Send `event` to every player and spectator. Only public frames go
+    /// through here; anything private uses `send_to`.
+    pub fn broadcast(&self, event: &ServerEvent) {
+        let frame = event.to_frame();
         for p in self.players.values() {
             if let Some(addr) = &p.addr {
                 addr.do_send(ServerText(frame.clone()));
             }
         }
+        for s in self.spectators.values() {
+            s.addr.do_send(ServerText(frame.clone()));
+        }
     }
-    pub fn chat(&self, id: PlayerId, text: String) {
-        println!("Room::chat id={} phase={:?}", id, self.phase);
-        if self.phase == Phase::Night {
-            println!("Room::chat ignored (night)");
-            return;
+
+    /// Start watching the room as `id`; the spectator is sent the public
+    /// state right away and every broadcast from then on.
+    pub fn add_spectator(&mut self, id: String, addr: Addr<WsClient>, full_view: bool) {
+        log::info!("Room::add_spectator id={} full_view={}", id, full_view);
+        let state = ServerEvent::Spectating(SpectatorState {
+            room_id: self.id.clone(),
+            phase: self.phase,
+            round: self.round,
+            deadline: self.phase_deadline,
+            players: self.player_statuses(),
+            delay: full_view.then_some(self.config.spectator_delay),
+        });
+        addr.do_send(ServerText(state.to_frame()));
+        self.spectators.insert(id, Spectator { addr, full_view });
+    }
+
+    pub fn remove_spectator(&mut self, id: &str) {
+        if self.spectators.remove(id).is_some() {
+            log::info!("Room::remove_spectator id={}", id);
         }
-        if let Some(sender) = self.players.get(&id) {
-            if !sender.is_alive {
-                println!("Room::chat ignored (dead)");
-                return;
-            }
-        } else {
-            println!("Room::chat ignored (unknown)");
+    }
+
+    /// Queue the current roles and liveness for full-view spectators,
+    /// delivered `spectator_delay` seconds from now.
+    fn schedule_full_view(&self) {
+        if !self.spectators.values().any(|s| s.full_view) {
             return;
         }
-        let frame =
-            json!({"type":1,"target":"chat","arguments":[{"from":id,"text":text}]}).to_string();
-        println!("Room::chat broadcast frame={}", frame);
-        for p in self.players.values() {
-            if p.is_alive {
-                if let Some(addr) = &p.addr {
-                    addr.do_send(ServerText(frame.clone()));
+        let view = ServerEvent::SpectatorView(self.full_view());
+        self.schedule(
+            Duration::from_secs(self.config.spectator_delay),
+            move |room| {
+                let frame = view.to_frame();
+                for s in room.spectators.values().filter(|s| s.full_view) {
+                    s.addr.do_send(ServerText(frame.clone()));
                 }
-            }
+            },
+        );
+    }
+
+    fn full_view(&self) -> FullView {
+        FullView {
+            phase: self.phase,
+            round: self.round,
+            players: self
+                .seated()
+                .map(|p| PlayerRole {
+                    id: p.id.clone(),
+                    role: p.role,
+                    alive: p.is_alive,
+                })
+                .collect(),
         }
     }
-    pub fn initiate_shuffle(&mut self) {
-        println!("Room::initiate_shuffle");
-        self.shuffle_order = self.players.keys().cloned().collect();
-        println!(
-            "Room::initiate_shuffle shuffle_order = {:?}",
-            self.shuffle_order
+
+    /// Tell `id` why their `target` frame was refused.
+    pub fn reject(&self, id: &PlayerId, code: ErrorCode, target: &str, message: &str) {
+        log::warn!(
+            "Room::reject id={} target={} code={:?}: {}",
+            id,
+            target,
+            code,
+            message
         );
-        self.shuffle_index = 0;
+        self.send_to(id, &ServerEvent::error(code, target, message));
+    }
+
+    fn broadcast_lobby(&self) {
+        log::info!("Room::broadcast_lobby players={}", self.players.len());
+        self.broadcast(&ServerEvent::Lobby(LobbyState {
+            room_id: self.id.clone(),
+            host: self.host.clone(),
+            config: LobbyConfig {
+                mode: self.config.role_mode,
+                shuffle_policy: self.config.shuffle_policy,
+                vote_rule: self.config.vote_rule,
+                tie_rule: self.config.tie_rule,
+                players: self.config.player_count(),
+                werewolves: self.config.roles.werewolves,
+                seers: self.config.roles.seers,
+                villagers: self.config.roles.villagers,
+            },
+            players: self
+                .seated()
+                .map(|p| LobbyPlayer {
+                    id: p.id.clone(),
+                    name: p.name.clone(),
+                    ready: p.is_ready,
+                    connected: p.addr.is_some(),
+                })
+                .collect(),
+        }));
+    }
+
+    pub fn set_ready(&mut self, id: PlayerId, ready: bool) {
+        log::info!("Room::set_ready id={} ready={}", id, ready);
+        if let Some(player) = self.players.get_mut(&id) {
+            player.is_ready = ready;
+            self.record(GameEvent::Ready {
+                player_id: id.clone(),
+                ready,
+            });
+            self.persist();
+            self.broadcast_lobby();
+        } else {
+            log::info!("Room::set_ready unknown id={}", id);
+        }
+        self.try_start()
+    }
+
+    pub fn try_start(&mut self) {
+        let ready_count = self.players.values().filter(|p| p.is_ready).count();
+        let key_count = self.public_keys.len();
+        let expected = self.config.player_count();
+        log::info!(
+            "Room::try_start game_started={} ready_count={} key_count={}",
+            self.game_started,
+            ready_count,
+            key_count
+        );
+        if self.game_started {
+            return;
+        }
+        let everyone_ready = self.players.len() == expected && ready_count == expected;
+        match self.config.role_mode {
+            RoleMode::Plaintext if everyone_ready => {
+                self.game_started = true;
+                self.start_game();
+            }
+            RoleMode::MentalPoker if everyone_ready && key_count == expected => {
+                self.game_started = true;
+                self.start_dealing();
+            }
+            _ => {}
+        }
+    }
+
+    /// Plaintext mode: deal the roles ourselves and go straight to night.
+    fn start_game(&mut self) {
+        log::info!("Room::start_game");
+        self.record(GameEvent::GameStarted {
+            seats: self.seats.clone(),
+        });
+        self.broadcast_game_start();
+        self.deal_plaintext_roles();
+        self.round = 1;
+        self.enter_phase(Phase::Night);
+    }
+
+    /// Mental-poker mode: announce the game, then wait for the deck to be
+    /// shuffled, picked, decrypted and every role revealed.
+    fn start_dealing(&mut self) {
+        log::info!("Room::start_dealing");
+        self.record(GameEvent::GameStarted {
+            seats: self.seats.clone(),
+        });
+        self.phase = Phase::Dealing;
+        self.broadcast_game_start();
+        self.persist();
+    }
+
+    /// Dealing has begun and the aggregate key still has to be computed.
+    pub fn needs_aggregation(&self) -> bool {
+        self.phase == Phase::Dealing && self.aggregation == Aggregation::Idle
+    }
+
+    /// Claim the aggregation job: returns the keys to combine, or `None` if
+    /// it is not needed or someone else is already running it.
+    pub fn begin_aggregation(&mut self) -> Option<Vec<String>> {
+        if !self.needs_aggregation() {
+            return None;
+        }
+        log::info!("Room::begin_aggregation");
+        self.aggregation = Aggregation::Running;
+        Some(self.ordered_public_keys())
+    }
+
+    /// The prover answered. On success the shuffle starts; on failure every
+    /// player is told and the room waits for a `retryAggregation`.
+    pub fn finish_aggregation(&mut self, result: Result<String, String>) {
+        if self.aggregation != Aggregation::Running || self.phase != Phase::Dealing {
+            return;
+        }
+        match result {
+            Ok(agg_pk) => {
+                log::info!("Room::finish_aggregation agg_pk={:?}", agg_pk);
+                self.record(GameEvent::KeysAggregated {
+                    agg_pk: agg_pk.clone(),
+                });
+                self.agg_pk = agg_pk;
+                self.aggregation = Aggregation::Done;
+                self.initiate_shuffle();
+            }
+            Err(reason) => {
+                log::warn!("Room::finish_aggregation failed: {}", reason);
+                self.broadcast(&ServerEvent::AggregationFailed(Reason {
+                    reason: reason.clone(),
+                }));
+                self.aggregation = Aggregation::Failed(reason);
+            }
+        }
+        self.persist();
+    }
+
+    /// Any seated player may ask for another attempt after a failure.
+    pub fn retry_aggregation(&mut self, id: &PlayerId) -> Result<(), String> {
+        if !self.players.contains_key(id) {
+            return Err("unknown player".into());
+        }
+        if !matches!(self.aggregation, Aggregation::Failed(_)) {
+            return Err("key aggregation has not failed".into());
+        }
+        log::info!("Room::retry_aggregation id={}", id);
+        self.aggregation = Aggregation::Idle;
+        Ok(())
+    }
+
+    fn deal_plaintext_roles(&mut self) {
+        let ids = self.seats.clone();
+        let roles = match assign_roles(&ids, &self.config.roles) {
+            Ok(roles) => roles,
+            Err(e) => {
+                log::warn!("Room::deal_plaintext_roles failed: {}", e);
+                return;
+            }
+        };
+        for (id, role) in &roles {
+            if let Some(player) = self.players.get_mut(id) {
+                player.role = Some(*role);
+            }
+        }
+        self.record(GameEvent::RolesDealt {
+            roles: roles.clone(),
+        });
+        let wolves: Vec<PlayerId> = roles
+            .iter()
+            .filter(|(_, role)| **role == Role::Werewolf)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for (id, role) in &roles {
+            self.send_to(id, &role_card(*role, &wolves));
+        }
+    }
 
-        let frame = json!({
-            "type": 1,
-            "target": "startShuffle",
-            "arguments": [{
-                "agg_pk":   self.agg_pk,
-                "deck":     self.deck_state
-            }]
+    /// The claim `id` must prove to show they hold `role`: their taken card
+    /// in the final deck decrypts to `role`'s message.
+    pub fn card_claim(&self, id: &PlayerId, role: Role) -> Result<CardClaim, String> {
+        let card = self
+            .taken_cards
+            .get(id)
+            .and_then(|&index| self.deck_state.get(index))
+            .ok_or("you hold no card")?;
+        Ok(CardClaim {
+            agg_pk: self.agg_pk.clone(),
+            card: card.clone(),
+            message: role.card_message().to_string(),
         })
-        .to_string();
+    }
 
-        if let Some(player_id) = self.shuffle_order.get(0) {
-            println!(
-                "Room::initiate_shuffle sending startShuffle to player {}...",
-                player_id
-            );
-            if let Some(player) = self.players.get(player_id) {
-                println!("Room::initiate_shuffle player found");
-                if let Some(addr) = &player.addr {
-                    println!("Room::initiate_shuffle sending frame");
-                    addr.do_send(ServerText(frame));
+    /// Mental-poker mode: `id` proved with `claim` that their decrypted
+    /// card is the role it names. Night falls once every player has
+    /// revealed.
+    pub fn reveal_role(&mut self, id: &PlayerId, claim: CardClaim) -> Result<(), String> {
+        let role = Role::from_card_message(&claim.message).ok_or("no role has that message")?;
+        log::info!("Room::reveal_role id={} role={:?}", id, role);
+        if self.card_claim(id, role)? != claim {
+            return Err("the claim no longer matches your card".into());
+        }
+        if self.phase != Phase::Dealing {
+            return Err("roles are only revealed while dealing".into());
+        }
+        let decrypted = self
+            .decrypt_ctx
+            .get(id)
+            .is_some_and(|ctx| ctx.helpers.is_empty());
+        if !decrypted {
+            return Err("your card is not fully decrypted yet".into());
+        }
+        let player = self.players.get(id).ok_or("unknown player")?;
+        if player.role.is_some() {
+            return Err("role already revealed".into());
+        }
+        let claimed = self
+            .players
+            .values()
+            .filter(|p| p.role == Some(role))
+            .count();
+        let dealt = self
+            .config
+            .roles
+            .roles()
+            .iter()
+            .filter(|r| **r == role)
+            .count();
+        if claimed >= dealt {
+            return Err(format!("every {:?} card is already claimed", role));
+        }
+        if let Some(player) = self.players.get_mut(id) {
+            player.role = Some(role);
+        }
+        self.record(GameEvent::RoleRevealed {
+            player_id: id.clone(),
+            role,
+        });
+        self.persist();
+        self.send_to(id, &role_card(role, &[]));
+
+        if self.players.values().all(|p| p.role.is_some()) {
+            log::info!("Room::reveal_role all roles revealed");
+            self.round = 1;
+            self.enter_phase(Phase::Night);
+        }
+        Ok(())
+    }
+
+    fn broadcast_game_start(&self) {
+        self.broadcast(&ServerEvent::GameStart(message::GameStart {
+            players: self
+                .seated()
+                .enumerate()
+                .map(|(seat, p)| PlayerInfo {
+                    id: p.id.clone(),
+                    name: p.name.clone(),
+                    seat,
+                })
+                .collect(),
+            mode: self.config.role_mode,
+        }));
+    }
+
+    /// Switch to a timed phase, announce it with its deadline, and arm the
+    /// timer that resolves it if the players don't finish first.
+    fn enter_phase(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.phase_seq += 1;
+        if phase == Phase::Night {
+            self.night_done = false;
+        }
+        let duration = self.config.phase_duration(phase);
+        let now = self.clock.now_millis();
+        self.phase_deadline = duration.map(|d| now + d.as_millis() as u64);
+
+        log::info!("Room::enter_phase phase={:?} round={}", phase, self.round);
+        self.record(GameEvent::PhaseStarted {
+            phase,
+            round: self.round,
+        });
+        self.broadcast(&ServerEvent::Phase(PhaseChange {
+            phase,
+            round: self.round,
+            duration: duration.map(|d| d.as_secs()),
+            deadline: self.phase_deadline,
+        }));
+
+        if let Some(duration) = duration {
+            self.schedule_phase_timeout(duration);
+        }
+        self.schedule_full_view();
+        self.persist();
+    }
+
+    fn schedule_phase_timeout(&self, duration: Duration) {
+        let seq = self.phase_seq;
+        self.schedule(duration, move |room| room.on_phase_timeout(seq));
+    }
+
+    /// Run `f` on this room after `delay`, unless the room is gone by then.
+    fn schedule(&self, delay: Duration, f: impl FnOnce(&mut Room) + Send + 'static) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            log::debug!("Room::schedule no runtime, timer not armed");
+            return;
+        };
+        let room = self.self_ref.clone();
+        runtime.spawn(async move {
+            actix_web::rt::time::sleep(delay).await;
+            if let Some(room) = room.upgrade() {
+                f(&mut room.lock().unwrap());
+            }
+        });
+    }
+
+    /// Time ran out on phase number `seq`. Missing night actions count as
+    /// no kill / no peek, missing votes as abstentions.
+    pub fn on_phase_timeout(&mut self, seq: u64) {
+        if seq != self.phase_seq {
+            return;
+        }
+        log::info!("Room::on_phase_timeout phase={:?}", self.phase);
+        match self.phase {
+            Phase::Night => self.resolve_night(),
+            Phase::Day => self.resolve_day(),
+            Phase::Reveal => self.finish_reveal(),
+            Phase::Lobby | Phase::Dealing | Phase::GameOver => {}
+        }
+    }
+
+    /// Record a night action. The actor's role, dealt in plaintext or
+    /// proven with `revealRole` before the first night, must match the
+    /// action: only werewolves `kill`, only seers `peek`. No per-action
+    /// proof is asked for, as the card proof would be the same every night.
+    pub fn night_action(
+        &mut self,
+        id: PlayerId,
+        action: String,
+        target: PlayerId,
+    ) -> Result<(), String> {
+        log::info!(
+            "Room::night_action id={} action={} target={}",
+            id,
+            action,
+            target
+        );
+        if self.phase != Phase::Night {
+            return Err("night actions are only accepted at night".into());
+        }
+        let actor = self.players.get(&id).ok_or("unknown player")?;
+        if !actor.is_alive {
+            return Err("dead players cannot act".into());
+        }
+        let needed = night_role(&action)?;
+        if actor.role != Some(needed) {
+            return Err(format!("only a {:?} can {action}", needed));
+        }
+        if !self.players.get(&target).is_some_and(|p| p.is_alive) {
+            return Err("target must be a living player".into());
+        }
+        if target == id {
+            return Err("cannot target yourself".into());
+        }
+        self.record(GameEvent::NightAction {
+            player_id: id.clone(),
+            action: action.clone(),
+            target: target.clone(),
+        });
+        self.pending_night.insert(id.clone(), (action, target));
+        self.persist();
+        if self.pending_night.len() == self.required_night_actions() {
+            self.resolve_night();
+        }
+        Ok(())
+    }
+
+    fn required_night_actions(&self) -> usize {
+        let count = self
+            .players
+            .values()
+            .filter(|p| p.is_alive)
+            .filter(|p| matches!(p.role, Some(Role::Werewolf) | Some(Role::Seer)))
+            .count();
+        log::info!("Room::required_night_actions count={}", count);
+        count
+    }
+
+    pub fn resolve_night(&mut self) {
+        log::info!("Room::resolve_night pending={}", self.pending_night.len());
+        if self.night_done {
+            return;
+        }
+        self.night_done = true;
+        let killed = self.night_victim();
+        if let Some(ref id) = killed {
+            log::info!("Room::resolve_night killed={}", id);
+            self.kill(id, DeathCause::Killed);
+        }
+        for (actor, (action, target)) in &self.pending_night {
+            if action == "peek" {
+                log::info!("Room::resolve_night peek actor={} target={}", actor, target);
+                if let Some(target_player) = self.players.get(target) {
+                    self.send_to(
+                        actor,
+                        &ServerEvent::PeekResult(PeekResult {
+                            target: target.clone(),
+                            role: target_player.role,
+                        }),
+                    );
                 }
             }
         }
+        log::info!("Room::resolve_night broadcast nightEnd");
+        self.broadcast(&ServerEvent::NightEnd(NightEnd { killed }));
+        if let Some(winner) = self.check_win() {
+            log::info!("Room::resolve_night win={}", winner);
+            self.end_game(winner);
+            return;
+        }
+        self.pending_night.clear();
+        self.enter_phase(Phase::Day);
+    }
+
+    /// The player most werewolves chose to kill. A split between wolves
+    /// goes to the tied target seated first, so the outcome does not depend
+    /// on the order the actions arrived in.
+    fn night_victim(&self) -> Option<PlayerId> {
+        let mut counts: HashMap<&PlayerId, usize> = HashMap::new();
+        for (action, target) in self.pending_night.values() {
+            if action == "kill" {
+                *counts.entry(target).or_default() += 1;
+            }
+        }
+        let mut victim: Option<(&PlayerId, usize)> = None;
+        for id in &self.seats {
+            let count = counts.get(id).copied().unwrap_or_default();
+            if count > 0 && victim.is_none_or(|(_, best)| count > best) {
+                victim = Some((id, count));
+            }
+        }
+        victim.map(|(id, _)| id.clone())
+    }
+
+    fn living_count(&self) -> usize {
+        let count = self.players.values().filter(|p| p.is_alive).count();
+        log::info!("Room::living_count ={}", count);
+        count
+    }
+
+    /// `voter` casts or changes their ballot: a vote against another living
+    /// player (one of the tied players in a runoff), or `None` to abstain.
+    pub fn vote(&mut self, voter: PlayerId, target: Option<PlayerId>) {
+        log::info!("Room::vote voter={} target={:?}", voter, target);
+        let frame = if target.is_some() { "vote" } else { "abstain" };
+        if let Err((code, message)) = self.may_vote(&voter, target.as_ref()) {
+            return self.reject(&voter, code, frame, message);
+        }
+        self.record(GameEvent::Vote {
+            voter: voter.clone(),
+            target: target.clone(),
+        });
+        self.votes.insert(voter, target);
+        self.votes_changed();
+    }
+
+    /// Take back `voter`'s ballot, leaving them undecided again.
+    pub fn retract_vote(&mut self, voter: PlayerId) {
+        log::info!("Room::retract_vote voter={}", voter);
+        if self.phase != Phase::Day {
+            let message = "votes are only accepted during the day";
+            return self.reject(&voter, ErrorCode::WrongPhase, "retractVote", message);
+        }
+        if self.votes.remove(&voter).is_none() {
+            let message = "you have not voted";
+            return self.reject(&voter, ErrorCode::NotAllowed, "retractVote", message);
+        }
+        self.record(GameEvent::VoteRetracted { voter });
+        self.votes_changed();
+    }
+
+    fn may_vote(
+        &self,
+        voter: &PlayerId,
+        target: Option<&PlayerId>,
+    ) -> Result<(), (ErrorCode, &'static str)> {
+        if self.phase != Phase::Day {
+            return Err((
+                ErrorCode::WrongPhase,
+                "votes are only accepted during the day",
+            ));
+        }
+        if !self.players.get(voter).is_some_and(|p| p.is_alive) {
+            return Err((ErrorCode::NotAllowed, "dead players cannot vote"));
+        }
+        let Some(target) = target else {
+            return Ok(());
+        };
+        if target == voter {
+            return Err((ErrorCode::NotAllowed, "you cannot vote for yourself"));
+        }
+        if !self.players.get(target).is_some_and(|p| p.is_alive) {
+            return Err((
+                ErrorCode::NotAllowed,
+                "you can only vote for a living player",
+            ));
+        }
+        if self.runoff.as_ref().is_some_and(|c| !c.contains(target)) {
+            return Err((
+                ErrorCode::NotAllowed,
+                "the runoff is between the tied players only",
+            ));
+        }
+        Ok(())
+    }
+
+    /// `id` died during the day: their ballot and every ballot against them
+    /// no longer count, and the day may now be decided without them.
+    fn drop_ballots(&mut self, id: &PlayerId) {
+        log::info!("Room::drop_ballots id={}", id);
+        self.votes
+            .retain(|voter, target| voter != id && target.as_ref() != Some(id));
+        if let Some(candidates) = &mut self.runoff {
+            candidates.retain(|c| c != id);
+        }
+        self.votes_changed();
+    }
+
+    /// Announce the new tally, and end the day once every living player
+    /// has a ballot in or a majority is already locked in.
+    fn votes_changed(&mut self) {
+        log::info!("Room::votes_changed broadcast voteUpdate");
+        self.persist();
+        self.broadcast(&ServerEvent::VoteUpdate(self.vote_tally()));
+        if self.votes.len() == self.living_count() || self.majority_locked() {
+            self.resolve_day();
+        }
+    }
+
+    /// Someone has votes from more than half of the living: whatever the
+    /// remaining ballots say, they are the one lynched.
+    fn majority_locked(&self) -> bool {
+        let living = self.living_count();
+        self.vote_counts().values().any(|&count| count * 2 > living)
+    }
+
+    fn vote_counts(&self) -> HashMap<&PlayerId, usize> {
+        let mut counts = HashMap::new();
+        for target in self.votes.values().flatten() {
+            *counts.entry(target).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Votes per candidate and every ballot cast, both in seat order.
+    fn vote_tally(&self) -> VoteTally {
+        let counts = self.vote_counts();
+        let votes = self
+            .seats
+            .iter()
+            .filter_map(|id| {
+                counts.get(id).map(|&count| VoteCount {
+                    player_id: id.clone(),
+                    count,
+                })
+            })
+            .collect();
+        let ballots = self
+            .seats
+            .iter()
+            .filter_map(|voter| {
+                self.votes.get(voter).map(|target| Ballot {
+                    voter: voter.clone(),
+                    target: target.clone(),
+                })
+            })
+            .collect();
+        VoteTally { votes, ballots }
+    }
+
+    /// What the ballots cast so far decide under the room's vote rules.
+    fn day_outcome(&self) -> DayOutcome {
+        let counts = self.vote_counts();
+        let top = counts.values().copied().max().unwrap_or(0);
+        if top == 0 {
+            return DayOutcome::NoLynch;
+        }
+        let mut leaders: Vec<PlayerId> = self
+            .seats
+            .iter()
+            .filter(|id| counts.get(id) == Some(&top))
+            .cloned()
+            .collect();
+        let runoff_allowed = self.config.tie_rule == TieRule::Runoff && self.runoff.is_none();
+        match self.config.vote_rule {
+            VoteRule::Majority if top * 2 > self.living_count() => {
+                DayOutcome::Lynch(leaders.remove(0))
+            }
+            VoteRule::Plurality if leaders.len() == 1 => DayOutcome::Lynch(leaders.remove(0)),
+            _ if leaders.len() > 1 && runoff_allowed => DayOutcome::Runoff(leaders),
+            _ => DayOutcome::NoLynch,
+        }
+    }
+
+    /// A tie under the runoff rule: the day starts over with a vote between
+    /// the tied players only.
+    fn start_runoff(&mut self, candidates: Vec<PlayerId>) {
+        log::info!("Room::start_runoff candidates={:?}", candidates);
+        self.votes.clear();
+        self.broadcast(&ServerEvent::Runoff(RunoffVote {
+            candidates: candidates.clone(),
+        }));
+        self.runoff = Some(candidates);
+        self.enter_phase(Phase::Day);
+    }
+
+    fn resolve_day(&mut self) {
+        log::info!(
+            "Room::resolve_day votes={} pending_night={} players_alive={}",
+            self.votes.len(),
+            self.pending_night.len(),
+            self.living_count()
+        );
+        let lynch_opt = match self.day_outcome() {
+            DayOutcome::Runoff(candidates) => return self.start_runoff(candidates),
+            DayOutcome::Lynch(id) => {
+                log::info!("Room::resolve_day lynched={}", id);
+                self.kill(&id, DeathCause::Lynched);
+                Some(id)
+            }
+            DayOutcome::NoLynch => {
+                log::info!("Room::resolve_day lynch skip");
+                None
+            }
+        };
+        self.runoff = None;
+        log::info!("Room::resolve_day broadcast dayEnd");
+        self.broadcast(&ServerEvent::DayEnd(DayEnd { lynched: lynch_opt }));
+        if let Some(winner) = self.check_win() {
+            log::info!("Room::resolve_day win={}", winner);
+            self.end_game(winner);
+            return;
+        }
+        self.votes.clear();
+        self.pending_night.clear();
+        self.round += 1;
+        self.enter_phase(Phase::Night);
+    }
+
+    fn check_win(&self) -> Option<&'static str> {
+        let result = self.winner_for(|p| p.role);
+        log::info!("Room::check_win -> {:?}", result);
+        result
+    }
+
+    /// The side that has won if `role_of` gives every player's role.
+    fn winner_for(&self, role_of: impl Fn(&Player) -> Option<Role>) -> Option<&'static str> {
+        let wolf_alive = self
+            .players
+            .values()
+            .any(|p| p.is_alive && role_of(p) == Some(Role::Werewolf));
+        let villagers_alive = self
+            .players
+            .values()
+            .any(|p| p.is_alive && role_of(p) != Some(Role::Werewolf));
+        match (wolf_alive, villagers_alive) {
+            (false, true) => Some("villagers"),
+            (true, false) => Some("werewolves"),
+            _ => None,
+        }
+    }
+
+    /// `winner` has won. Plaintext games announce it straight away; in
+    /// mental poker everyone first proves their card in the reveal phase.
+    fn end_game(&mut self, winner: &'static str) {
+        log::info!("Room::end_game winner={}", winner);
+        self.winner = Some(winner);
+        if self.config.role_mode == RoleMode::MentalPoker {
+            self.reveals.clear();
+            self.enter_phase(Phase::Reveal);
+            return;
+        }
+        let roles = self
+            .players
+            .iter()
+            .map(|(id, p)| (id.clone(), p.role))
+            .collect();
+        self.broadcast_game_over(winner, roles);
+        self.phase = Phase::GameOver;
+        self.persist();
+        self.schedule_full_view();
+    }
+
+    /// The claim `id` must prove to reveal `role` at the end of the game:
+    /// their taken card in the final deck decrypts to `role`'s message.
+    pub fn reveal_claim(&self, id: &PlayerId, role: Role) -> Result<CardClaim, String> {
+        if self.phase != Phase::Reveal {
+            return Err("cards are only revealed once the game is over".into());
+        }
+        if self.reveals.contains_key(id) {
+            return Err("card already revealed".into());
+        }
+        self.card_claim(id, role)
+    }
+
+    /// Record a reveal whose proof of `claim` checked out, and announce the
+    /// game once every card is revealed.
+    pub fn accept_reveal(&mut self, id: &PlayerId, claim: CardClaim) -> Result<(), String> {
+        let role = Role::from_card_message(&claim.message).ok_or("no role has that message")?;
+        if self.reveal_claim(id, role)? != claim {
+            return Err("the claim no longer matches your card".into());
+        }
+        log::info!("Room::accept_reveal id={} role={:?}", id, role);
+        self.record(GameEvent::CardRevealed {
+            player_id: id.clone(),
+            role,
+        });
+        self.reveals.insert(id.clone(), role);
+        self.persist();
+        if self.reveals.len() == self.seats.len() {
+            self.finish_reveal();
+        }
+        Ok(())
+    }
+
+    /// End the reveal phase and announce the winner with the proven roles.
+    /// Only proven cards decide the winner: a player who did not reveal
+    /// counts as holding no role, so a living werewolf who hides their card
+    /// forfeits for their side. The result reached during play stands only
+    /// when the proven cards leave both sides standing. Players who did not
+    /// reveal are listed without a role.
+    fn finish_reveal(&mut self) {
+        if self.phase != Phase::Reveal {
+            return;
+        }
+        log::info!("Room::finish_reveal revealed={}", self.reveals.len());
+        let proven = self.winner_for(|p| self.reveals.get(&p.id).copied());
+        let Some(winner) = proven.or(self.winner) else {
+            return;
+        };
+        self.winner = Some(winner);
+        let roles = self
+            .players
+            .keys()
+            .map(|id| (id.clone(), self.reveals.get(id).copied()))
+            .collect();
+        self.broadcast_game_over(winner, roles);
+        self.phase = Phase::GameOver;
+        self.persist();
+        self.schedule_full_view();
+    }
+
+    fn broadcast_game_over(&mut self, winner: &str, roles: HashMap<PlayerId, Option<Role>>) {
+        log::info!("Room::broadcast_game_over winner={}", winner);
+        self.record(GameEvent::GameOver {
+            winner: winner.to_string(),
+            roles: roles.clone(),
+        });
+        let event = ServerEvent::GameOver(GameOver {
+            winner: winner.to_string(),
+            roles,
+        });
+        log::debug!("Room::broadcast_game_over event={:?}", event);
+        self.broadcast(&event);
+    }
+    pub fn chat(&self, id: PlayerId, text: String, channel: ChatChannel) {
+        log::info!(
+            "Room::chat id={} channel={:?} phase={:?}",
+            id,
+            channel,
+            self.phase
+        );
+        let Some(sender) = self.players.get(&id) else {
+            log::info!("Room::chat ignored (unknown)");
+            return;
+        };
+        if let Err((code, message)) = self.may_chat(sender, channel) {
+            self.reject(&id, code, "chat", message);
+            return;
+        }
+        let frame = ServerEvent::Chat(ChatLine {
+            from: id,
+            text,
+            channel,
+        })
+        .to_frame();
+        log::info!("Room::chat broadcast frame={}", frame);
+        for p in self.players.values().filter(|p| listens(p, channel)) {
+            if let Some(addr) = &p.addr {
+                addr.do_send(ServerText(frame.clone()));
+            }
+        }
+        if channel == ChatChannel::Public {
+            for s in self.spectators.values() {
+                s.addr.do_send(ServerText(frame.clone()));
+            }
+        }
+    }
+
+    /// Whether `sender` may post on `channel` now: they must be one of its
+    /// listeners, public chat is closed at night, and the wolves' channel
+    /// only exists while the game is running.
+    fn may_chat(
+        &self,
+        sender: &Player,
+        channel: ChatChannel,
+    ) -> Result<(), (ErrorCode, &'static str)> {
+        match channel {
+            ChatChannel::Public if self.phase == Phase::Night => {
+                Err((ErrorCode::WrongPhase, "public chat is closed at night"))
+            }
+            ChatChannel::Wolves if !matches!(self.phase, Phase::Night | Phase::Day) => Err((
+                ErrorCode::WrongPhase,
+                "the wolves channel is only open during the game",
+            )),
+            _ if !listens(sender, channel) => Err((
+                ErrorCode::NotAllowed,
+                match channel {
+                    ChatChannel::Public => "dead players can only use the dead channel",
+                    ChatChannel::Wolves => "only living werewolves can use the wolves channel",
+                    ChatChannel::Dead => "only dead players can use the dead channel",
+                },
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn initiate_shuffle(&mut self) {
+        log::info!("Room::initiate_shuffle");
+        self.shuffle_order = self.seats.clone();
+        log::info!(
+            "Room::initiate_shuffle shuffle_order = {:?}",
+            self.shuffle_order
+        );
+        self.shuffle_index = 0;
+        self.original_deck = self.deck_state.clone();
+        self.start_shuffle_turn();
+    }
+
+    /// Hand the deck to whoever's turn it is and arm their turn timer, or
+    /// announce the finished deck once everyone has shuffled.
+    fn start_shuffle_turn(&mut self) {
+        self.shuffle_seq += 1;
+        self.shuffle_attempts = 0;
+        let Some(player_id) = self.shuffle_order.get(self.shuffle_index).cloned() else {
+            log::info!("Room::start_shuffle_turn shuffle complete");
+            self.picking = true;
+            self.broadcast(&ServerEvent::ShuffleComplete(ShuffledDeck {
+                deck: self.deck_state.clone(),
+            }));
+            return;
+        };
+        log::info!(
+            "Room::start_shuffle_turn sending startShuffle to player {}...",
+            player_id
+        );
+        self.send_to(&player_id, &self.shuffle_turn());
+
+        let seq = self.shuffle_seq;
+        self.schedule(
+            Duration::from_secs(self.config.shuffle_turn_timeout),
+            move |room| room.on_shuffle_timeout(seq),
+        );
+    }
+
+    /// What `id` claims by answering their `startShuffle` with `shuffled`,
+    /// to be proven before the deck is replaced.
+    pub fn shuffle_claim(
+        &self,
+        id: &PlayerId,
+        shuffled: Vec<[String; 2]>,
+    ) -> Result<ShuffleClaim, String> {
+        if self.phase != Phase::Dealing || self.shuffle_order.get(self.shuffle_index) != Some(id) {
+            return Err("it is not your turn to shuffle".into());
+        }
+        Ok(ShuffleClaim {
+            agg_pk: self.agg_pk.clone(),
+            deck: self.deck_state.clone(),
+            shuffled,
+        })
+    }
+
+    /// `id`'s proof for `claim` verified: take their deck and move on.
+    /// Fails if the turn moved on while the proof was checked.
+    pub fn accept_shuffle(&mut self, id: &PlayerId, claim: ShuffleClaim) -> Result<(), String> {
+        if self.shuffle_order.get(self.shuffle_index) != Some(id) {
+            return Err("it is not your turn to shuffle".into());
+        }
+        if claim.deck != self.deck_state || claim.agg_pk != self.agg_pk {
+            return Err("the deck changed while your proof was checked".into());
+        }
+        log::info!("Room::accept_shuffle id={}", id);
+        self.record(GameEvent::ShuffleAccepted {
+            player_id: id.clone(),
+            deck: claim.shuffled.clone(),
+        });
+        self.deck_state = claim.shuffled;
+        self.shuffle_index += 1;
+        self.start_shuffle_turn();
+        self.persist();
+        Ok(())
+    }
+
+    /// `id`'s shuffle proof failed. After too many failures in their turn
+    /// the room's shuffle policy kicks in.
+    pub fn reject_shuffle(&mut self, id: &PlayerId, reason: &str) {
+        self.send_to(
+            id,
+            &ServerEvent::ShuffleRejected(Reason {
+                reason: reason.to_string(),
+            }),
+        );
+        if self.shuffle_order.get(self.shuffle_index) != Some(id) {
+            return;
+        }
+        self.shuffle_attempts += 1;
+        log::info!(
+            "Room::reject_shuffle id={} attempts={}",
+            id,
+            self.shuffle_attempts
+        );
+        if self.shuffle_attempts >= self.config.shuffle_attempts {
+            let reason = format!("{} invalid shuffle proofs", self.shuffle_attempts);
+            self.on_shuffle_fault(id, reason);
+        }
+    }
+
+    /// Shuffle turn number `seq` ran out of time.
+    pub fn on_shuffle_timeout(&mut self, seq: u64) {
+        if seq != self.shuffle_seq || self.phase != Phase::Dealing {
+            return;
+        }
+        if let Some(id) = self.shuffle_order.get(self.shuffle_index).cloned() {
+            self.on_shuffle_fault(&id, "shuffle turn timed out".into());
+        }
+    }
+
+    /// `id` lost their shuffle turn; apply the configured policy.
+    fn on_shuffle_fault(&mut self, id: &PlayerId, reason: String) {
+        let policy = self.config.shuffle_policy;
+        log::warn!(
+            "Room::on_shuffle_fault id={} policy={:?}: {}",
+            id,
+            policy,
+            reason
+        );
+        self.record(GameEvent::ShuffleFault {
+            player_id: id.clone(),
+            reason: reason.clone(),
+            policy,
+        });
+        match policy {
+            ShufflePolicy::Skip => {
+                self.shuffle_index += 1;
+                self.broadcast_shuffle_fault(id, reason, policy);
+                self.start_shuffle_turn();
+            }
+            ShufflePolicy::Restart => {
+                self.shuffle_order.retain(|p| p != id);
+                self.shuffle_index = 0;
+                self.deck_state = self.original_deck.clone();
+                self.broadcast_shuffle_fault(id, reason, policy);
+                self.start_shuffle_turn();
+            }
+            ShufflePolicy::Kick => {
+                self.shuffle_order.clear();
+                self.broadcast_shuffle_fault(id, reason, policy);
+                self.kick_from_dealing(id);
+            }
+        }
+        self.persist();
+    }
+
+    fn broadcast_shuffle_fault(&self, id: &PlayerId, reason: String, policy: ShufflePolicy) {
+        let fault = ShuffleFault {
+            player_id: id.clone(),
+            reason,
+            policy,
+            order: self.shuffle_order.clone(),
+        };
+        self.broadcast(&match policy {
+            ShufflePolicy::Restart => ServerEvent::ShuffleRestarted(fault),
+            ShufflePolicy::Skip | ShufflePolicy::Kick => ServerEvent::ShuffleAborted(fault),
+        });
+    }
+
+    /// Drop `id` and send the room back to the lobby: their key is part of
+    /// the aggregate, so the deck cannot be dealt without them.
+    /// Everyone left has to ready up again for the next attempt.
+    fn kick_from_dealing(&mut self, id: &PlayerId) {
+        log::info!("Room::kick_from_dealing id={}", id);
+        if let Some(addr) = self.players.get(id).and_then(|p| p.addr.as_ref()) {
+            addr.do_send(Unseated);
+        }
+        self.remove_from_lobby(id);
+
+        self.game_started = false;
+        self.phase = Phase::Lobby;
+        self.aggregation = Aggregation::Idle;
+        self.agg_pk.clear();
+        self.shuffle_order.clear();
+        self.shuffle_index = 0;
+        self.shuffle_seq += 1;
+        self.shuffle_attempts = 0;
+        self.original_deck.clear();
+        self.deck_state = self.config.roles.deck();
+        self.picking = false;
+        self.taken_cards.clear();
+        self.decrypt_ctx.clear();
+        self.flagged_helpers.clear();
+        self.reveals.clear();
+        self.key_challenges.clear();
+        for player in self.players.values_mut() {
+            player.is_ready = false;
+            player.role = None;
+        }
+        self.persist();
+
+        if self.players.is_empty() {
+            self.abandoned = true;
+            return;
+        }
+        self.broadcast_lobby();
+    }
+
+    /// `startShuffle` for whoever's turn it is: the current deck and the
+    /// key to re-encrypt it under.
+    pub fn shuffle_turn(&self) -> ServerEvent {
+        ServerEvent::StartShuffle(ShuffleTurn {
+            agg_pk: self.agg_pk.clone(),
+            deck: self.deck_state.clone(),
+        })
+    }
+
+    /// `id` picks deck index `card`. Picks are simultaneous: the first to
+    /// claim a card gets it, later claims are denied and may pick again.
+    pub fn pick_card(&mut self, id: &PlayerId, card: usize) {
+        log::info!("Room::pick_card id={} card={}", id, card);
+        if !self.picking {
+            let message = "cards can only be picked once the shuffle is complete";
+            return self.reject(id, ErrorCode::WrongPhase, "pickCard", message);
+        }
+        if card >= self.deck_state.len() {
+            let message = format!("the deck has {} cards", self.deck_state.len());
+            return self.reject(id, ErrorCode::BadPayload, "pickCard", &message);
+        }
+        if self.taken_cards.contains_key(id) {
+            let message = "you already picked a card";
+            return self.reject(id, ErrorCode::NotAllowed, "pickCard", message);
+        }
+        if self.taken_cards.values().any(|&c| c == card) {
+            self.send_to(
+                id,
+                &ServerEvent::CardTaken(CardTaken {
+                    status: AckStatus::Denied,
+                    card,
+                }),
+            );
+            return;
+        }
+
+        self.taken_cards.insert(id.clone(), card);
+        self.record(GameEvent::CardPicked {
+            player_id: id.clone(),
+            card,
+        });
+        self.persist();
+        self.send_to(
+            id,
+            &ServerEvent::CardTaken(CardTaken {
+                status: AckStatus::Ok,
+                card,
+            }),
+        );
+        self.broadcast(&self.cards_taken());
+
+        if self.taken_cards.len() == self.seats.len() {
+            self.picking = false;
+            self.begin_decryption();
+        }
+    }
+
+    fn cards_taken(&self) -> ServerEvent {
+        let mut taken: Vec<usize> = self.taken_cards.values().copied().collect();
+        taken.sort_unstable();
+        ServerEvent::CardsTaken(TakenCards {
+            remaining: self.seats.len() - taken.len(),
+            taken,
+        })
+    }
+
+    /// Every card is picked: queue the other players, in seat order, to
+    /// strip their layer off each card and ask the first helpers to start.
+    pub fn begin_decryption(&mut self) {
+        log::info!("Room::begin_decryption cards={}", self.taken_cards.len());
+        for (owner, &card) in &self.taken_cards {
+            let helpers: VecDeque<PlayerId> = self
+                .seats
+                .iter()
+                .filter(|id| *id != owner)
+                .cloned()
+                .collect();
+            self.decrypt_ctx.insert(
+                owner.clone(),
+                DecryptCtx {
+                    helpers,
+                    current_cipher: self.deck_state[card].clone(),
+                    components: Vec::new(),
+                    card_index: card,
+                },
+            );
+        }
+        self.persist();
+        for owner in &self.seats {
+            self.send_need_decrypt(owner);
+        }
+    }
+
+    /// Ask the next helper in `requester`'s queue to strip their layer.
+    pub fn send_need_decrypt(&self, requester: &PlayerId) {
+        let Some(ctx) = self.decrypt_ctx.get(requester) else {
+            return;
+        };
+        if let Some(helper) = ctx.helpers.front() {
+            log::info!(
+                "Room::send_need_decrypt helper={} for={}",
+                helper,
+                requester
+            );
+            self.send_to(
+                helper,
+                &ServerEvent::NeedDecrypt(DecryptRequest {
+                    for_player: requester.clone(),
+                    card: ctx.card_index,
+                    cipher: ctx.current_cipher.clone(),
+                }),
+            );
+        }
+    }
+
+    /// What `helper` claims by relaying `partial` and `component` for
+    /// `requester`'s card, to be proven before the relay moves on.
+    pub fn decryption_claim(
+        &self,
+        helper: &PlayerId,
+        requester: &PlayerId,
+        partial: [String; 2],
+        component: String,
+    ) -> Result<DecryptionClaim, String> {
+        let ctx = self
+            .decrypt_ctx
+            .get(requester)
+            .ok_or_else(|| format!("no card of {requester} is waiting for decryption"))?;
+        if ctx.helpers.front() != Some(helper) {
+            return Err("not your turn to decrypt this card".into());
+        }
+        let public_key = self
+            .public_keys
+            .get(helper)
+            .ok_or("you have no registered public key")?;
+        Ok(DecryptionClaim {
+            public_key: public_key.clone(),
+            cipher: ctx.current_cipher.clone(),
+            partial,
+            component,
+        })
+    }
+
+    /// `helper`'s proof for `claim` verified: advance `requester`'s card to
+    /// the next helper, or hand it back once every layer is off.
+    pub fn accept_partial_decryption(
+        &mut self,
+        helper: &PlayerId,
+        requester: &PlayerId,
+        claim: DecryptionClaim,
+    ) -> Result<(), String> {
+        let ctx = self
+            .decrypt_ctx
+            .get_mut(requester)
+            .ok_or("no card is waiting for decryption")?;
+        if ctx.helpers.front() != Some(helper) || ctx.current_cipher != claim.cipher {
+            return Err("the card moved on while your proof was checked".into());
+        }
+        log::info!(
+            "Room::accept_partial_decryption helper={} for={}",
+            helper,
+            requester
+        );
+        ctx.components.push(claim.component.clone());
+        ctx.current_cipher = claim.partial.clone();
+        ctx.helpers.pop_front();
+        let done = ctx.helpers.is_empty();
+        let card = ctx.card_index;
+        let components = ctx.components.clone();
+        self.persist();
+
+        if !done {
+            self.send_need_decrypt(requester);
+            return Ok(());
+        }
+        self.send_to(
+            requester,
+            &ServerEvent::PartialReady(PartialDecryption {
+                card,
+                partial: claim.partial,
+                component: claim.component,
+                components,
+            }),
+        );
+        self.send_to(requester, &ServerEvent::AllPartsReady(CardRef { card }));
+        Ok(())
+    }
+
+    /// `helper`'s partial decryption for `requester` failed verification.
+    /// The card does not advance and everyone learns who cheated or erred.
+    pub fn flag_helper(&mut self, helper: &PlayerId, requester: &PlayerId, reason: &str) {
+        log::warn!(
+            "Room::flag_helper helper={} for={}: {}",
+            helper,
+            requester,
+            reason
+        );
+        self.flagged_helpers.insert(helper.clone());
+        let card = self
+            .decrypt_ctx
+            .get(requester)
+            .map(|ctx| ctx.card_index)
+            .unwrap_or_default();
+        self.broadcast(&ServerEvent::DecryptionFlagged(FlaggedDecryption {
+            player_id: helper.clone(),
+            for_player: requester.clone(),
+            card,
+            reason: reason.to_string(),
+        }));
+    }
+}
+
+/// Whether `player` receives lines sent on `channel`.
+fn listens(player: &Player, channel: ChatChannel) -> bool {
+    match channel {
+        ChatChannel::Public => player.is_alive,
+        ChatChannel::Wolves => player.is_alive && player.role == Some(Role::Werewolf),
+        ChatChannel::Dead => !player.is_alive,
+    }
+}
+
+/// The role allowed to take a night `action`.
+fn night_role(action: &str) -> Result<Role, String> {
+    match action {
+        "kill" => Ok(Role::Werewolf),
+        "peek" => Ok(Role::Seer),
+        _ => Err(format!("unknown night action {action}")),
+    }
+}
+
+/// The winning side named `name`, as `winner_for` spells it.
+fn side(name: &str) -> Option<&'static str> {
+    ["villagers", "werewolves"]
+        .into_iter()
+        .find(|side| *side == name)
+}
+
+/// Private `role` frame; werewolves also learn who their packmates are.
+fn role_card(role: Role, wolves: &[PlayerId]) -> ServerEvent {
+    ServerEvent::Role(RoleCard {
+        role,
+        werewolves: (role == Role::Werewolf).then(|| wolves.to_vec()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::store::FileStore;
+
+    fn seat(room: &mut Room, id: &str, role: Role) {
+        room.record(GameEvent::PlayerJoined {
+            player_id: id.to_string(),
+            name: id.to_uppercase(),
+        });
+        room.seats.push(id.to_string());
+        room.players.insert(
+            id.to_string(),
+            Player {
+                id: id.to_string(),
+                name: id.to_uppercase(),
+                role: Some(role),
+                is_ready: true,
+                is_alive: true,
+                addr: None,
+                session_token: format!("token-{id}"),
+                connection_epoch: 0,
+            },
+        );
+    }
+
+    fn four_player_room() -> Room {
+        let mut room = Room::new("TEST".into());
+        seat(&mut room, "w", Role::Werewolf);
+        seat(&mut room, "s", Role::Seer);
+        seat(&mut room, "v1", Role::Villager);
+        seat(&mut room, "v2", Role::Villager);
+        room.game_started = true;
+        room.round = 1;
+        room
+    }
+
+    fn unroled_room(mode: RoleMode) -> Room {
+        let mut room = Room::new("TEST".into());
+        room.config.role_mode = mode;
+        for id in ["a", "b", "c", "d"] {
+            seat(&mut room, id, Role::Villager);
+            room.players.get_mut(id).unwrap().role = None;
+        }
+        room
+    }
+
+    #[test]
+    fn plaintext_start_deals_every_role() {
+        let mut room = unroled_room(RoleMode::Plaintext);
+        room.try_start();
+
+        assert_eq!(room.phase, Phase::Night);
+        let wolves = room
+            .players
+            .values()
+            .filter(|p| p.role == Some(Role::Werewolf))
+            .count();
+        assert!(room.players.values().all(|p| p.role.is_some()));
+        assert_eq!(wolves, 1);
+    }
+
+    #[test]
+    fn mental_poker_waits_for_keys_then_reveals() {
+        let mut room = unroled_room(RoleMode::MentalPoker);
+        room.try_start();
+        assert_eq!(room.phase, Phase::Lobby, "keys are still missing");
+
+        for id in ["a", "b", "c", "d"] {
+            room.public_keys.insert(id.into(), format!("pk-{id}"));
+        }
+        room.try_start();
+        assert_eq!(room.phase, Phase::Dealing);
+        assert_eq!(room.begin_aggregation(), Some(room.ordered_public_keys()));
+        assert_eq!(room.begin_aggregation(), None, "already running");
+        room.finish_aggregation(Ok("agg".into()));
+        assert_eq!(room.agg_pk, "agg");
+
+        dealt_and_decrypted(&mut room);
+        reveal_role(&mut room, "a", Role::Werewolf).unwrap();
+        assert!(reveal_role(&mut room, "b", Role::Werewolf).is_err());
+        assert!(reveal_role(&mut room, "a", Role::Seer).is_err());
+        reveal_role(&mut room, "b", Role::Seer).unwrap();
+        reveal_role(&mut room, "c", Role::Villager).unwrap();
+        assert_eq!(room.phase, Phase::Dealing);
+
+        reveal_role(&mut room, "d", Role::Villager).unwrap();
+        assert_eq!(room.phase, Phase::Night);
+    }
+
+    /// Every player of an `unroled_room` took card `i` and has it decrypted.
+    fn dealt_and_decrypted(room: &mut Room) {
+        for (i, id) in ["a", "b", "c", "d"].iter().enumerate() {
+            room.taken_cards.insert(id.to_string(), i);
+            room.decrypt_ctx.insert(
+                id.to_string(),
+                DecryptCtx {
+                    helpers: VecDeque::new(),
+                    current_cipher: ["0".into(), "0".into()],
+                    components: Vec::new(),
+                    card_index: i,
+                },
+            );
+        }
+    }
+
+    fn reveal_role(room: &mut Room, id: &str, role: Role) -> Result<(), String> {
+        let claim = room.card_claim(&id.into(), role)?;
+        room.reveal_role(&id.into(), claim)
+    }
+
+    #[test]
+    fn role_claims_are_bound_to_the_taken_card() {
+        let mut room = unroled_room(RoleMode::MentalPoker);
+        room.game_started = true;
+        room.phase = Phase::Dealing;
+        room.agg_pk = "agg".into();
+        room.deck_state = (0..4).map(|i| [format!("c{i}"), format!("m{i}")]).collect();
+        dealt_and_decrypted(&mut room);
+
+        let a: PlayerId = "a".into();
+        let wolf = room.card_claim(&a, Role::Werewolf).unwrap();
+        assert_eq!(wolf.public_inputs(), ["agg", "c0", "m0", "10"]);
+        assert_ne!(
+            wolf.public_inputs(),
+            room.card_claim(&a, Role::Seer).unwrap().public_inputs(),
+            "a proof for one role does not verify for another"
+        );
+
+        let bs_card = room.card_claim(&"b".into(), Role::Werewolf).unwrap();
+        assert!(
+            room.reveal_role(&a, bs_card).is_err(),
+            "a proof for another player's card is refused"
+        );
+        let other_key = CardClaim {
+            agg_pk: "other".into(),
+            ..wolf.clone()
+        };
+        assert!(room.reveal_role(&a, other_key).is_err());
+        assert!(room.players["a"].role.is_none());
+
+        room.reveal_role(&a, wolf).unwrap();
+        assert_eq!(room.players["a"].role, Some(Role::Werewolf));
+    }
+
+    #[test]
+    fn keys_need_a_signed_challenge_and_freeze_at_start() {
+        let mut room = unroled_room(RoleMode::MentalPoker);
+        let a: PlayerId = "a".into();
+        let first = room.key_challenge(&a, "pk-a".into()).unwrap();
+        assert!(room.public_keys.is_empty(), "not registered until signed");
+
+        let second = room.key_challenge(&a, "pk-a".into()).unwrap();
+        assert_ne!(first.nonce, second.nonce);
+        assert!(room.accept_key(&a, first).is_err(), "stale nonce");
+        room.accept_key(&a, second).unwrap();
+        assert_eq!(room.public_keys[&a], "pk-a");
+        assert!(room.key_claim(&a).is_err(), "a nonce is signed once");
+
+        assert!(room.key_challenge(&"b".into(), "pk-a".into()).is_err());
+        let bad = room.key_challenge(&"b".into(), "pk-b".into()).unwrap();
+        room.reject_key(&"b".into(), &bad);
+        assert!(room.accept_key(&"b".into(), bad).is_err());
+
+        for id in ["b", "c", "d"] {
+            let claim = room.key_challenge(&id.into(), format!("pk-{id}")).unwrap();
+            room.accept_key(&id.into(), claim).unwrap();
+        }
+        room.try_start();
+        assert_eq!(room.phase, Phase::Dealing);
+        assert!(room.key_challenge(&a, "pk-new".into()).is_err());
+        assert_eq!(room.public_keys[&a], "pk-a");
+    }
+
+    #[test]
+    fn failed_aggregation_waits_for_retry() {
+        let mut room = unroled_room(RoleMode::MentalPoker);
+        for id in ["a", "b", "c", "d"] {
+            room.public_keys.insert(id.into(), format!("pk-{id}"));
+        }
+        room.try_start();
+
+        assert!(room.begin_aggregation().is_some());
+        room.finish_aggregation(Err("prover down".into()));
+        assert_eq!(room.aggregation, Aggregation::Failed("prover down".into()));
+        assert_eq!(room.begin_aggregation(), None);
+
+        assert!(room.retry_aggregation(&"nobody".into()).is_err());
+        room.retry_aggregation(&"b".into()).unwrap();
+        assert!(room.begin_aggregation().is_some());
+        room.finish_aggregation(Ok("agg".into()));
+
+        assert_eq!(room.aggregation, Aggregation::Done);
+        assert_eq!(room.shuffle_index, 0);
+        assert_eq!(room.shuffle_order.len(), 4);
+    }
+
+    #[test]
+    fn night_timeout_resolves_without_actions() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Night);
+        let seq = room.phase_seq;
+        assert!(room.phase_deadline.is_some());
+
+        room.on_phase_timeout(seq - 1);
+        assert_eq!(room.phase, Phase::Night, "stale timer must be ignored");
+
+        room.on_phase_timeout(seq);
+        assert_eq!(room.phase, Phase::Day);
+        assert!(
+            room.players.values().all(|p| p.is_alive),
+            "no kill by default"
+        );
+    }
+
+    #[test]
+    fn day_timeout_counts_missing_votes_as_abstain() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Day);
+        room.vote("s".into(), Some("w".into()));
+        assert_eq!(room.phase, Phase::Day);
+
+        room.on_phase_timeout(room.phase_seq);
+
+        assert!(!room.players["w"].is_alive);
+        assert_eq!(room.phase, Phase::GameOver);
+    }
+
+    #[test]
+    fn lobby_player_is_removed_after_grace() {
+        let mut room = Room::new("TEST".into());
+        seat(&mut room, "a", Role::Villager);
+        seat(&mut room, "b", Role::Villager);
+        room.host = Some("a".into());
+
+        room.on_grace_expired(&"a".to_string(), 1);
+        assert!(room.players.contains_key("a"), "stale epoch is ignored");
+
+        room.on_grace_expired(&"a".to_string(), 0);
+        assert!(!room.players.contains_key("a"));
+        assert_eq!(room.host.as_deref(), Some("b"));
+
+        room.on_grace_expired(&"b".to_string(), 0);
+        assert!(room.is_finished(), "empty lobby is abandoned");
+    }
+
+    #[test]
+    fn player_gone_mid_game_counts_as_dead() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Day);
+
+        room.on_grace_expired(&"w".to_string(), 0);
+
+        assert!(!room.players["w"].is_alive);
+        assert_eq!(room.phase, Phase::GameOver);
+    }
+
+    #[test]
+    fn snapshot_reports_own_state() {
+        let mut room = four_player_room();
+        room.taken_cards.insert("s".into(), 2);
+        room.enter_phase(Phase::Night);
+
+        let snap = room.snapshot_for(&"s".to_string());
+
+        assert_eq!(snap.phase, Phase::Night);
+        assert_eq!(snap.role, Some(Role::Seer));
+        assert_eq!(snap.taken_card, Some(2));
+        assert_eq!(snap.players.len(), 4);
+    }
+
+    #[test]
+    fn night_actions_must_match_known_roles() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Night);
+
+        assert!(room
+            .night_action("v1".into(), "kill".into(), "s".into())
+            .is_err());
+        assert!(room
+            .night_action("s".into(), "kill".into(), "v1".into())
+            .is_err());
+        assert!(room
+            .night_action("w".into(), "kill".into(), "nobody".into())
+            .is_err());
+        assert!(room
+            .night_action("w".into(), "kill".into(), "w".into())
+            .is_err());
+
+        room.night_action("w".into(), "kill".into(), "v1".into())
+            .unwrap();
+        room.night_action("s".into(), "peek".into(), "w".into())
+            .unwrap();
+
+        assert_eq!(room.phase, Phase::Day);
+        assert!(!room.players["v1"].is_alive);
+    }
+
+    #[test]
+    fn wolves_kill_their_plurality_target_or_the_first_seated_on_a_split() {
+        let night = |kills: &[(&str, &str)]| {
+            let mut room = Room::new("TEST".into());
+            for (wolf, _) in kills {
+                seat(&mut room, wolf, Role::Werewolf);
+            }
+            for id in ["v1", "v2", "v3", "v4", "v5"] {
+                seat(&mut room, id, Role::Villager);
+            }
+            room.game_started = true;
+            room.enter_phase(Phase::Night);
+            for (wolf, target) in kills {
+                room.night_action(wolf.to_string(), "kill".into(), target.to_string())
+                    .unwrap();
+            }
+            room.seated()
+                .filter(|p| !p.is_alive)
+                .map(|p| p.id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(night(&[("w1", "v3"), ("w2", "v3")]), ["v3"]);
+        assert_eq!(night(&[("w1", "v4"), ("w2", "v2")]), ["v2"]);
+        assert_eq!(
+            night(&[("w2", "v2"), ("w1", "v4")]),
+            ["v2"],
+            "arrival order does not matter"
+        );
+        assert_eq!(night(&[("w1", "v1"), ("w2", "v3"), ("w3", "v3")]), ["v3"]);
+    }
+
+    #[test]
+    fn night_actions_need_a_known_role() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Night);
+        room.players.get_mut("w").unwrap().role = None;
+
+        assert!(room
+            .night_action("w".into(), "kill".into(), "v1".into())
+            .is_err());
+        assert!(room
+            .night_action("w".into(), "bite".into(), "v1".into())
+            .is_err());
+        room.night_action("s".into(), "peek".into(), "w".into())
+            .unwrap();
+    }
+
+    #[test]
+    fn second_night_resolves_again() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Night);
+        room.on_phase_timeout(room.phase_seq);
+        room.on_phase_timeout(room.phase_seq);
+
+        assert_eq!(room.phase, Phase::Night);
+        assert_eq!(room.round, 2);
+
+        room.on_phase_timeout(room.phase_seq);
+        assert_eq!(room.phase, Phase::Day);
+    }
+
+    fn decrypting_room() -> Room {
+        let mut room = unroled_room(RoleMode::MentalPoker);
+        for id in ["a", "b", "c", "d"] {
+            room.public_keys.insert(id.into(), format!("pk-{id}"));
+        }
+        room.decrypt_ctx.insert(
+            "a".into(),
+            DecryptCtx {
+                helpers: VecDeque::from(["b".to_string(), "c".to_string()]),
+                current_cipher: ["x".into(), "y".into()],
+                components: Vec::new(),
+                card_index: 1,
+            },
+        );
+        room
+    }
+
+    #[test]
+    fn leaving_mid_decryption_sends_the_room_back_to_the_lobby() {
+        let mut room = decrypting_room();
+        room.game_started = true;
+        room.phase = Phase::Dealing;
+        room.taken_cards.insert("a".into(), 1);
+
+        room.on_grace_expired(&"b".to_string(), 0);
+
+        assert_eq!(room.phase, Phase::Lobby);
+        assert!(!room.game_started);
+        assert!(!room.players.contains_key("b"));
+        assert!(room.decrypt_ctx.is_empty());
+        assert!(room.taken_cards.is_empty());
+        assert!(room.players.values().all(|p| !p.is_ready));
+    }
+
+    fn layer(n: &str) -> [String; 2] {
+        [format!("x{n}"), format!("y{n}")]
+    }
+
+    #[test]
+    fn decryption_claim_binds_helper_key_and_cipher() {
+        let room = decrypting_room();
+        let claim = room
+            .decryption_claim(&"b".into(), &"a".into(), layer("b"), "cb".into())
+            .unwrap();
+
+        assert_eq!(
+            claim.public_inputs(),
+            ["pk-b", "x", "y", "xb", "yb", "cb"].map(String::from)
+        );
+        assert!(room
+            .decryption_claim(&"c".into(), &"a".into(), layer("c"), "cc".into())
+            .is_err());
+    }
+
+    #[test]
+    fn accepted_partial_decryption_advances_the_card() {
+        let mut room = decrypting_room();
+        let claim = room
+            .decryption_claim(&"b".into(), &"a".into(), layer("b"), "cb".into())
+            .unwrap();
+        room.accept_partial_decryption(&"b".into(), &"a".into(), claim.clone())
+            .unwrap();
+
+        let ctx = &room.decrypt_ctx["a"];
+        assert_eq!(ctx.current_cipher, layer("b"));
+        assert_eq!(ctx.components, ["cb"]);
+        assert_eq!(ctx.helpers, ["c"]);
+        assert!(
+            room.accept_partial_decryption(&"b".into(), &"a".into(), claim)
+                .is_err(),
+            "a replayed claim is stale"
+        );
+    }
+
+    #[test]
+    fn restored_room_carries_on_decrypting() {
+        let dir = std::env::temp_dir().join(format!("wss-restore-{}", std::process::id()));
+        let store: SharedStore = Arc::new(FileStore::new(&dir));
+        let mut room = decrypting_room();
+        room.aggregation = Aggregation::Running;
+        room.set_store(store.clone());
+        let claim = room
+            .decryption_claim(&"b".into(), &"a".into(), layer("b"), "cb".into())
+            .unwrap();
+        room.accept_partial_decryption(&"b".into(), &"a".into(), claim)
+            .unwrap();
+
+        let saved = store.load_all().unwrap().pop().unwrap();
+        let restored = Room::restore(saved, Arc::new(SystemClock), Some(store));
+        let restored = restored.lock().unwrap();
+        assert_eq!(restored.decrypt_ctx, room.decrypt_ctx);
+        assert_eq!(restored.seats, room.seats);
+        assert_eq!(
+            restored.players["a"].session_token,
+            room.players["a"].session_token
+        );
+        assert!(restored.players.values().all(|p| p.addr.is_none()));
+        assert!(restored
+            .decryption_claim(&"c".into(), &"a".into(), layer("c"), "cc".into())
+            .is_ok());
+        assert!(
+            matches!(restored.aggregation, Aggregation::Failed(_)),
+            "the prover call in flight is lost"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ballots_and_night_actions_are_saved() {
+        let dir = std::env::temp_dir().join(format!("wss-ballots-{}", std::process::id()));
+        let store: SharedStore = Arc::new(FileStore::new(&dir));
+        let mut room = four_player_room();
+        room.set_store(store.clone());
+        room.enter_phase(Phase::Night);
+        room.night_action("s".into(), "peek".into(), "w".into())
+            .unwrap();
+        assert_eq!(store.load_all().unwrap()[0].pending_night.len(), 1);
+
+        room.enter_phase(Phase::Day);
+        room.vote("w".into(), Some("s".into()));
+        let saved = store.load_all().unwrap().pop().unwrap();
+        assert_eq!(saved.votes["w"], Some("s".into()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reveals_and_lobby_leaves_are_saved() {
+        let dir = std::env::temp_dir().join(format!("wss-reveals-{}", std::process::id()));
+        let store: SharedStore = Arc::new(FileStore::new(&dir));
+        let mut room = revealing_room();
+        room.set_store(store.clone());
+        room.on_grace_expired(&"w".to_string(), 0);
+        reveal(&mut room, "s", Role::Seer).unwrap();
+        let saved = store.load_all().unwrap().pop().unwrap();
+        assert_eq!(saved.reveals.get("s"), Some(&Role::Seer));
+
+        let mut lobby = unroled_room(RoleMode::Plaintext);
+        lobby.id = "LOBBY".into();
+        lobby.set_store(store.clone());
+        lobby.on_grace_expired(&"a".to_string(), 0);
+        let saved = store.load_all().unwrap().remove(0);
+        assert_eq!(saved.id, "LOBBY");
+        assert_eq!(saved.seats.len(), 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn flagged_helper_does_not_advance_the_card() {
+        let mut room = decrypting_room();
+        room.flag_helper(&"b".into(), &"a".into(), "invalid decryption proof");
+
+        assert!(room.flagged_helpers.contains("b"));
+        assert_eq!(room.decrypt_ctx["a"].current_cipher, ["x", "y"]);
+        assert_eq!(room.decrypt_ctx["a"].helpers.front().unwrap(), "b");
+    }
+
+    #[test]
+    fn seat_order_drives_keys_shuffle_and_helpers() {
+        let mut room = unroled_room(RoleMode::MentalPoker);
+        room.seats = ["d", "b", "a", "c"].map(String::from).to_vec();
+        for id in ["a", "b", "c", "d"] {
+            room.public_keys.insert(id.into(), format!("pk-{id}"));
+        }
+
+        assert_eq!(
+            room.ordered_public_keys(),
+            ["pk-d", "pk-b", "pk-a", "pk-c"].map(String::from)
+        );
+        room.initiate_shuffle();
+        assert_eq!(room.shuffle_order, room.seats);
+
+        for (card, id) in ["a", "b", "c", "d"].iter().enumerate() {
+            room.taken_cards.insert(id.to_string(), card);
+        }
+        room.begin_decryption();
+        assert_eq!(room.decrypt_ctx["a"].helpers, ["d", "b", "c"]);
+        assert_eq!(room.decrypt_ctx["d"].helpers, ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn vote_tally_follows_seat_order() {
+        let mut room = four_player_room();
+        room.phase = Phase::Day;
+        room.votes.insert("v2".into(), Some("w".into()));
+        room.votes.insert("w".into(), Some("v1".into()));
+        room.votes.insert("s".into(), Some("w".into()));
+        room.votes.insert("v1".into(), None);
+
+        let tally = room.vote_tally();
+        let counted: Vec<_> = tally
+            .votes
+            .iter()
+            .map(|c| (c.player_id.as_str(), c.count))
+            .collect();
+        let voters: Vec<_> = tally.ballots.iter().map(|b| b.voter.as_str()).collect();
+
+        assert_eq!(counted, [("w", 2), ("v1", 1)]);
+        assert_eq!(voters, ["w", "s", "v1", "v2"]);
+        assert_eq!(tally.ballots[2].target, None, "v1 abstained");
+    }
+
+    fn shuffling_room(policy: ShufflePolicy) -> Room {
+        let mut room = unroled_room(RoleMode::MentalPoker);
+        room.config.shuffle_policy = policy;
+        for id in ["a", "b", "c", "d"] {
+            room.public_keys.insert(id.into(), format!("pk-{id}"));
+        }
+        room.try_start();
+        room.begin_aggregation();
+        room.finish_aggregation(Ok("agg".into()));
+        room
+    }
+
+    fn shuffled(room: &Room, tag: &str) -> Vec<[String; 2]> {
+        room.deck_state
+            .iter()
+            .map(|[a, b]| [format!("{a}{tag}"), format!("{b}{tag}")])
+            .collect()
+    }
+
+    #[test]
+    fn shuffle_timeout_skips_the_player() {
+        let mut room = shuffling_room(ShufflePolicy::Skip);
+        let deck = shuffled(&room, "a");
+        let claim = room.shuffle_claim(&"a".into(), deck.clone()).unwrap();
+        room.accept_shuffle(&"a".into(), claim).unwrap();
+
+        let stale = room.shuffle_seq - 1;
+        room.on_shuffle_timeout(stale);
+        assert_eq!(room.shuffle_index, 1, "stale timer is ignored");
+
+        room.on_shuffle_timeout(room.shuffle_seq);
+        assert_eq!(room.shuffle_order.get(room.shuffle_index).unwrap(), "c");
+        assert_eq!(room.deck_state, deck, "b's turn left the deck alone");
+    }
+
+    #[test]
+    fn repeated_invalid_proofs_restart_without_the_player() {
+        let mut room = shuffling_room(ShufflePolicy::Restart);
+        let original = room.deck_state.clone();
+        let deck = shuffled(&room, "a");
+        let claim = room.shuffle_claim(&"a".into(), deck).unwrap();
+        room.accept_shuffle(&"a".into(), claim).unwrap();
+
+        room.reject_shuffle(&"c".into(), "invalid proof");
+        assert_eq!(
+            room.shuffle_attempts, 0,
+            "out-of-turn failures do not count"
+        );
+        for _ in 0..room.config.shuffle_attempts {
+            room.reject_shuffle(&"b".into(), "invalid proof");
+        }
+
+        assert_eq!(room.shuffle_order, ["a", "c", "d"]);
+        assert_eq!(room.shuffle_index, 0);
+        assert_eq!(room.deck_state, original);
+    }
+
+    #[test]
+    fn kick_policy_sends_the_room_back_to_the_lobby() {
+        let mut room = shuffling_room(ShufflePolicy::Kick);
+        room.reject_shuffle(&"a".into(), "invalid proof");
+        room.on_shuffle_timeout(room.shuffle_seq);
+
+        assert_eq!(room.phase, Phase::Lobby);
+        assert!(!room.players.contains_key("a"));
+        assert_eq!(room.seats, ["b", "c", "d"]);
+        assert!(room.is_open(), "the seat can be refilled");
+        assert_eq!(room.aggregation, Aggregation::Idle);
+        assert!(room.shuffle_order.is_empty());
+        assert_eq!(room.shuffle_attempts, 0);
+        assert!(
+            room.players.values().all(|p| !p.is_ready),
+            "everyone readies up again"
+        );
+    }
+
+    #[test]
+    fn shuffle_claim_is_bound_to_the_current_deck() {
+        let mut room = shuffling_room(ShufflePolicy::Skip);
+        let deck = shuffled(&room, "a");
+        let claim = room.shuffle_claim(&"a".into(), deck.clone()).unwrap();
+
+        assert_eq!(claim.deck, room.deck_state);
+        assert_eq!(claim.public_inputs()[0], "agg");
+        assert_eq!(claim.public_inputs().len(), 1 + 4 * room.deck_state.len());
+        assert!(room.shuffle_claim(&"b".into(), deck.clone()).is_err());
+
+        room.accept_shuffle(&"a".into(), claim.clone()).unwrap();
+        assert!(
+            room.accept_shuffle(&"b".into(), claim).is_err(),
+            "a claim about an earlier deck is stale"
+        );
+        let next = room.shuffle_claim(&"b".into(), deck.clone()).unwrap();
+        assert_eq!(next.deck, deck, "b proves against the deck a produced");
+    }
+
+    fn picking_room() -> Room {
+        let mut room = shuffling_room(ShufflePolicy::Skip);
+        for id in ["a", "b", "c", "d"] {
+            let claim = room.shuffle_claim(&id.into(), shuffled(&room, id)).unwrap();
+            room.accept_shuffle(&id.into(), claim).unwrap();
+        }
+        room
+    }
+
+    #[test]
+    fn picks_wait_for_the_shuffle() {
+        let mut room = shuffling_room(ShufflePolicy::Skip);
+        room.pick_card(&"a".into(), 0);
+        assert!(room.taken_cards.is_empty());
+
+        let mut room = picking_room();
+        room.pick_card(&"a".into(), 0);
+        assert_eq!(room.taken_cards.get("a"), Some(&0));
+    }
+
+    #[test]
+    fn picks_are_bounded_unique_and_once_per_player() {
+        let mut room = picking_room();
+        room.pick_card(&"a".into(), room.deck_state.len());
+        assert!(room.taken_cards.is_empty(), "out of range");
+
+        room.pick_card(&"a".into(), 2);
+        room.pick_card(&"a".into(), 3);
+        assert_eq!(room.taken_cards.get("a"), Some(&2), "no second pick");
+
+        room.pick_card(&"b".into(), 2);
+        assert!(!room.taken_cards.contains_key("b"), "card already gone");
+        room.pick_card(&"b".into(), 1);
+        assert_eq!(room.taken_cards.get("b"), Some(&1));
+
+        let ServerEvent::CardsTaken(taken) = room.cards_taken() else {
+            unreachable!()
+        };
+        assert_eq!(taken.taken, [1, 2]);
+        assert_eq!(taken.remaining, 2);
+    }
+
+    #[test]
+    fn last_pick_starts_decryption() {
+        let mut room = picking_room();
+        for (card, id) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            room.pick_card(&id.into(), card);
+        }
+        assert!(!room.picking);
+        assert_eq!(room.decrypt_ctx.len(), 4);
+    }
+
+    fn revealing_room() -> Room {
+        let mut room = four_player_room();
+        room.config.role_mode = RoleMode::MentalPoker;
+        room.agg_pk = "agg".into();
+        room.deck_state = room.config.roles.deck();
+        for (card, id) in ["w", "s", "v1", "v2"].into_iter().enumerate() {
+            room.taken_cards.insert(id.into(), card);
+        }
+        room.enter_phase(Phase::Day);
+        room
+    }
+
+    fn reveal(room: &mut Room, id: &str, role: Role) -> Result<(), String> {
+        let claim = room.reveal_claim(&id.into(), role)?;
+        room.accept_reveal(&id.into(), claim)
+    }
+
+    #[test]
+    fn mental_poker_game_ends_with_proven_cards() {
+        let mut room = revealing_room();
+        assert!(reveal(&mut room, "s", Role::Seer).is_err(), "game still on");
+
+        room.on_grace_expired(&"w".to_string(), 0);
+        assert_eq!(room.phase, Phase::Reveal);
+        let claim = room.reveal_claim(&"s".into(), Role::Seer).unwrap();
+        assert_eq!(claim.public_inputs(), ["agg", "1", "1", "1"]);
+
+        reveal(&mut room, "w", Role::Werewolf).unwrap();
+        reveal(&mut room, "s", Role::Seer).unwrap();
+        assert!(reveal(&mut room, "s", Role::Seer).is_err(), "only once");
+        reveal(&mut room, "v1", Role::Villager).unwrap();
+        assert_eq!(room.phase, Phase::Reveal);
+        reveal(&mut room, "v2", Role::Villager).unwrap();
+        assert_eq!(room.phase, Phase::GameOver);
+    }
+
+    #[test]
+    fn proven_cards_decide_the_winner() {
+        let mut room = revealing_room();
+        room.taken_cards.insert("w".into(), 2);
+        room.taken_cards.insert("v1".into(), 0);
+        room.players.get_mut("s").unwrap().is_alive = false;
+        room.players.get_mut("v2").unwrap().is_alive = false;
+
+        room.on_grace_expired(&"w".to_string(), 0);
+        assert_eq!(room.winner, Some("villagers"), "as claimed in play");
+        reveal(&mut room, "v1", Role::Werewolf).unwrap();
+        room.on_phase_timeout(room.phase_seq);
+
+        assert_eq!(room.phase, Phase::GameOver);
+        assert_eq!(room.reveals.len(), 1, "the rest never revealed");
+        assert_eq!(room.winner, Some("werewolves"));
+    }
+
+    #[test]
+    fn unrevealed_cards_do_not_count() {
+        let mut room = revealing_room();
+        room.players.get_mut("v1").unwrap().is_alive = false;
+        room.players.get_mut("v2").unwrap().is_alive = false;
+
+        room.on_grace_expired(&"s".to_string(), 0);
+        assert_eq!(room.winner, Some("werewolves"), "as claimed in play");
+        room.on_phase_timeout(room.phase_seq);
+
+        assert_eq!(room.phase, Phase::GameOver);
+        assert!(room.reveals.is_empty());
+        assert_eq!(room.winner, Some("villagers"), "the hidden wolf forfeits");
+    }
+
+    #[test]
+    fn chat_channels_check_role_and_life() {
+        let mut room = four_player_room();
+        room.players.get_mut("v2").unwrap().is_alive = false;
+        let may = |room: &Room, id: &str, channel| {
+            room.may_chat(&room.players[id], channel)
+                .map_err(|(code, _)| code)
+        };
+
+        room.enter_phase(Phase::Night);
+        assert_eq!(
+            may(&room, "v1", ChatChannel::Public),
+            Err(ErrorCode::WrongPhase)
+        );
+        assert_eq!(may(&room, "w", ChatChannel::Wolves), Ok(()));
+        assert_eq!(
+            may(&room, "s", ChatChannel::Wolves),
+            Err(ErrorCode::NotAllowed)
+        );
+        assert_eq!(may(&room, "v2", ChatChannel::Dead), Ok(()));
+        assert_eq!(
+            may(&room, "v1", ChatChannel::Dead),
+            Err(ErrorCode::NotAllowed)
+        );
+
+        room.enter_phase(Phase::Day);
+        assert_eq!(may(&room, "v1", ChatChannel::Public), Ok(()));
+        assert_eq!(
+            may(&room, "v2", ChatChannel::Public),
+            Err(ErrorCode::NotAllowed)
+        );
+
+        let wolves: Vec<&str> = room
+            .seated()
+            .filter(|p| listens(p, ChatChannel::Wolves))
+            .map(|p| p.id.as_str())
+            .collect();
+        assert_eq!(wolves, ["w"]);
+        room.players.get_mut("w").unwrap().is_alive = false;
+        assert!(!listens(&room.players["w"], ChatChannel::Wolves));
+        assert!(listens(&room.players["w"], ChatChannel::Dead));
+    }
+
+    #[test]
+    fn votes_must_target_another_living_player() {
+        let mut room = four_player_room();
+        room.players.get_mut("v2").unwrap().is_alive = false;
+        room.enter_phase(Phase::Day);
+
+        room.vote("s".into(), Some("s".into()));
+        room.vote("s".into(), Some("v2".into()));
+        room.vote("s".into(), Some("nobody".into()));
+        assert!(room.votes.is_empty());
+
+        room.vote("s".into(), None);
+        assert_eq!(room.votes.get("s"), Some(&None), "abstained");
+        room.vote("s".into(), Some("w".into()));
+        assert_eq!(room.votes.get("s"), Some(&Some("w".into())), "changed");
+        room.retract_vote("s".into());
+        assert!(room.votes.is_empty());
+    }
+
+    #[test]
+    fn locked_majority_ends_the_day_early() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Day);
+        room.vote("w".into(), Some("v1".into()));
+        room.vote("s".into(), Some("v1".into()));
+        assert_eq!(room.phase, Phase::Day, "two of four is no majority");
+
+        room.vote("v2".into(), Some("v1".into()));
+        assert!(!room.players["v1"].is_alive);
+        assert_eq!(room.phase, Phase::Night);
+    }
+
+    #[test]
+    fn majority_rule_needs_more_than_half() {
+        let mut room = four_player_room();
+        room.config.vote_rule = VoteRule::Majority;
+        room.enter_phase(Phase::Day);
+        room.vote("s".into(), Some("w".into()));
+        room.vote("v1".into(), Some("w".into()));
+        room.vote("w".into(), Some("s".into()));
+        room.vote("v2".into(), None);
+
+        assert!(room.players.values().all(|p| p.is_alive));
+        assert_eq!(room.phase, Phase::Night);
+    }
+
+    #[test]
+    fn leaving_mid_day_drops_ballots_by_and_against_the_leaver() {
+        let mut room = four_player_room();
+        room.enter_phase(Phase::Day);
+        room.vote("w".into(), Some("v2".into()));
+        room.vote("s".into(), Some("w".into()));
+        room.vote("v1".into(), Some("w".into()));
+        assert_eq!(room.phase, Phase::Day, "two of four is no majority");
+
+        room.on_grace_expired(&"v2".to_string(), 0);
+
+        assert!(!room.votes.contains_key("w"), "ballot against the leaver");
+        assert!(!room.players["w"].is_alive, "two of three is a majority");
+        assert_eq!(room.winner, Some("villagers"));
+    }
+
+    #[test]
+    fn tie_goes_to_a_runoff_between_the_tied() {
+        let mut room = four_player_room();
+        room.config.tie_rule = TieRule::Runoff;
+        room.enter_phase(Phase::Day);
+        room.vote("w".into(), Some("s".into()));
+        room.vote("v1".into(), Some("s".into()));
+        room.vote("s".into(), Some("w".into()));
+        room.vote("v2".into(), Some("w".into()));
+
+        assert_eq!(room.phase, Phase::Day);
+        assert_eq!(room.runoff.as_deref(), Some(&["w".into(), "s".into()][..]));
+        assert!(room.votes.is_empty());
+        room.vote("v1".into(), Some("v2".into()));
+        assert!(room.votes.is_empty(), "v2 is not in the runoff");
+
+        room.on_phase_timeout(room.phase_seq);
+        assert!(
+            room.players.values().all(|p| p.is_alive),
+            "no second runoff"
+        );
+        assert_eq!(room.runoff, None);
+        assert_eq!(room.phase, Phase::Night);
+    }
+
+    #[test]
+    fn replay_rebuilds_the_game_from_its_log() {
+        let mut room = unroled_room(RoleMode::Plaintext);
+        room.try_start();
+        let role_of = |room: &Room, role| {
+            room.seated()
+                .find(|p| p.role == Some(role))
+                .map(|p| p.id.clone())
+                .unwrap()
+        };
+        let wolf = role_of(&room, Role::Werewolf);
+        let seer = role_of(&room, Role::Seer);
+        let victim = role_of(&room, Role::Villager);
+        room.night_action(wolf.clone(), "kill".into(), victim.clone())
+            .unwrap();
+        room.night_action(seer.clone(), "peek".into(), wolf.clone())
+            .unwrap();
+        room.vote(seer.clone(), Some(wolf.clone()));
+
+        let text = room.log().to_json_lines();
+        let replayed = Room::replay(room.id.clone(), &EventLog::from_json_lines(&text).unwrap());
+
+        assert_eq!(replayed.phase, Phase::Day);
+        assert_eq!(replayed.round, room.round);
+        assert_eq!(replayed.seats, room.seats);
+        for p in room.seated() {
+            let q = &replayed.players[&p.id];
+            assert_eq!((q.role, q.is_alive), (p.role, p.is_alive), "{}", p.id);
+        }
+        assert!(!replayed.players[&victim].is_alive);
+        assert_eq!(replayed.votes(), room.votes());
+        assert_eq!(replayed.log(), room.log());
     }
 }
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::config::GameConfig,
+    room::{
+        log::EventLog,
+        room::{Aggregation, DecryptCtx},
+    },
+    types::{Phase, PlayerId, Role, RoomId, VoteMap},
+};
+
+pub type SharedStore = Arc<dyn RoomStore>;
+
+/// Durable storage for room state, so games survive a server restart.
+///
+/// Rooms save themselves while their lock is held, so implementations
+/// should be quick: a local file or database, not a network round trip.
+pub trait RoomStore: Send + Sync {
+    /// Replace the stored state of `snapshot.id`.
+    fn save(&self, snapshot: &RoomSnapshot) -> Result<(), String>;
+
+    /// Forget a room; deleting an unknown room is not an error.
+    fn delete(&self, id: &str) -> Result<(), String>;
+
+    /// Every stored room, for restoring on boot.
+    fn load_all(&self) -> Result<Vec<RoomSnapshot>, String>;
+}
+
+/// A seated player, minus their connection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeatSnapshot {
+    pub id: PlayerId,
+    pub name: String,
+    pub role: Option<Role>,
+    pub ready: bool,
+    pub alive: bool,
+    /// Kept so the player can `resume` after the restart.
+    pub session_token: String,
+}
+
+/// Everything about a room that outlives its connections and timers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSnapshot {
+    pub id: RoomId,
+    pub config: GameConfig,
+    pub host: Option<PlayerId>,
+    /// In seat order.
+    pub seats: Vec<SeatSnapshot>,
+    pub phase: Phase,
+    pub round: u32,
+    pub deadline: Option<u64>,
+    pub game_started: bool,
+    pub pending_night: HashMap<PlayerId, (String, String)>,
+    pub votes: VoteMap,
+    pub runoff: Option<Vec<PlayerId>>,
+    pub public_keys: HashMap<PlayerId, String>,
+    pub shuffle_order: Vec<PlayerId>,
+    pub shuffle_index: usize,
+    pub original_deck: Vec<[String; 2]>,
+    pub agg_pk: String,
+    pub aggregation: Aggregation,
+    pub deck: Vec<[String; 2]>,
+    pub picking: bool,
+    pub taken_cards: HashMap<PlayerId, usize>,
+    pub decrypt: HashMap<PlayerId, DecryptCtx>,
+    pub flagged_helpers: HashSet<PlayerId>,
+    pub night_done: bool,
+    pub winner: Option<String>,
+    pub reveals: HashMap<PlayerId, Role>,
+    pub log: EventLog,
+}
+
+/// Keeps each room as `<dir>/<room id>.json`.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+}
+
+impl RoomStore for FileStore {
+    /// Written to a temporary file first and renamed over the old one, so
+    /// a crash mid-write leaves the previous snapshot intact.
+    fn save(&self, snapshot: &RoomSnapshot) -> Result<(), String> {
+        let json = serde_json::to_string(snapshot).map_err(|e| e.to_string())?;
+        let path = self.path(&snapshot.id);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|()| std::fs::write(&tmp, json))
+            .and_then(|()| std::fs::rename(&tmp, &path))
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        let path = self.path(id);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("{}: {e}", path.display())),
+        }
+    }
+
+    /// A file that cannot be read or parsed is logged and skipped, so one
+    /// corrupt room does not keep the others from coming back.
+    fn load_all(&self) -> Result<Vec<RoomSnapshot>, String> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("{}: {e}", self.dir.display())),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => log::warn!("FileStore::load_all skipped {}: {e}", path.display()),
+            }
+        }
+        snapshots.sort_by(|a: &RoomSnapshot, b| a.id.cmp(&b.id));
+        Ok(snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::room::Room;
+
+    fn temp_store(name: &str) -> (PathBuf, FileStore) {
+        let dir = std::env::temp_dir().join(format!("wss-{name}-{}", std::process::id()));
+        (dir.clone(), FileStore::new(dir))
+    }
+
+    #[test]
+    fn file_store_saves_replaces_and_deletes() {
+        let (dir, store) = temp_store("store");
+        let mut snapshot = Room::new("ABC".into()).snapshot();
+        store.save(&snapshot).unwrap();
+        snapshot.round = 3;
+        store.save(&snapshot).unwrap();
+
+        assert_eq!(store.load_all().unwrap(), vec![snapshot]);
+        store.delete("ABC").unwrap();
+        store.delete("ABC").unwrap();
+        assert!(store.load_all().unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_files_are_skipped() {
+        let (dir, store) = temp_store("corrupt");
+        let snapshot = Room::new("ABC".into()).snapshot();
+        store.save(&snapshot).unwrap();
+        std::fs::write(dir.join("BAD.json"), "{\"id\":").unwrap();
+
+        assert_eq!(store.load_all().unwrap(), vec![snapshot]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_directory_holds_no_rooms() {
+        let (_, store) = temp_store("no-such-dir");
+        assert!(store.load_all().unwrap().is_empty());
+    }
+}
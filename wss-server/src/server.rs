@@ -0,0 +1,193 @@
+use std::future::{Future, IntoFuture};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use actix_web::{
+    dev::{Server, ServerHandle},
+    web, App, Error, HttpRequest, HttpResponse, HttpServer,
+};
+use actix_web_actors::ws as actix_ws;
+use uuid::Uuid;
+
+use crate::{
+    clock::{SharedClock, SystemClock},
+    config::ServerConfig,
+    prover::{http::HttpBackend, SharedBackend},
+    room::{
+        registry::{RoomRegistry, SharedRegistry},
+        store::{FileStore, SharedStore},
+    },
+    tls,
+    ws::client::WsClient,
+};
+
+type Routes = Arc<dyn Fn(&mut web::ServiceConfig) + Send + Sync>;
+
+async fn ws_handler(
+    req: HttpRequest,
+    stream: web::Payload,
+    registry: web::Data<SharedRegistry>,
+    backend: web::Data<SharedBackend>,
+) -> Result<HttpResponse, Error> {
+    let id = Uuid::new_v4().to_string();
+    let client = WsClient::new(id, registry.get_ref().clone(), backend.get_ref().clone());
+    actix_ws::start(client, &req, stream)
+}
+
+/// Sets up a game server from a [`ServerConfig`]. Anything not given
+/// explicitly comes from the config: the prover server, the state
+/// directory, the system clock.
+pub struct ServerBuilder {
+    config: ServerConfig,
+    backend: Option<SharedBackend>,
+    store: Option<SharedStore>,
+    clock: Option<SharedClock>,
+    routes: Vec<Routes>,
+}
+
+impl ServerBuilder {
+    pub fn new(config: ServerConfig) -> Self {
+        Self {
+            config,
+            backend: None,
+            store: None,
+            clock: None,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Verify proofs with `backend` instead of the configured prover server.
+    pub fn backend(mut self, backend: SharedBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Keep room state in `store` instead of the configured state directory.
+    pub fn store(mut self, store: SharedStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Stamp deadlines and log entries with `clock`.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Mount extra routes next to `/ws`, e.g. a health check.
+    pub fn configure(
+        mut self,
+        routes: impl Fn(&mut web::ServiceConfig) + Send + Sync + 'static,
+    ) -> Self {
+        self.routes.push(Arc::new(routes));
+        self
+    }
+
+    /// Restore stored rooms and bind the listener. The server runs once the
+    /// returned [`RunningServer`] is awaited.
+    pub fn build(self) -> io::Result<RunningServer> {
+        let config = self.config;
+        let backend = self
+            .backend
+            .unwrap_or_else(|| Arc::new(HttpBackend::new(config.prover.clone())));
+        let clock = self.clock.unwrap_or_else(|| Arc::new(SystemClock));
+        let store = self.store.or_else(|| {
+            let dir = config.state_dir.as_ref()?;
+            Some(Arc::new(FileStore::new(dir)) as SharedStore)
+        });
+
+        let mut registry = RoomRegistry::new()
+            .with_game_config(config.game)
+            .with_clock(clock);
+        if let Some(dir) = &config.log_dir {
+            registry = registry.with_log_dir(dir);
+        }
+        if let Some(store) = store {
+            registry = registry.with_store(store);
+        }
+        let registry: SharedRegistry = Arc::new(Mutex::new(registry));
+
+        let app_registry = registry.clone();
+        let routes = self.routes;
+        let server = HttpServer::new(move || {
+            let routes = routes.clone();
+            App::new()
+                .app_data(web::Data::new(app_registry.clone()))
+                .app_data(web::Data::new(backend.clone()))
+                .route("/ws", web::get().to(ws_handler))
+                .configure(move |cfg| {
+                    for route in &routes {
+                        route(cfg);
+                    }
+                })
+        })
+        .shutdown_timeout(config.shutdown_timeout);
+        let server = match &config.tls {
+            Some(tls) => server.bind_rustls_0_23(&config.bind, tls::server_config(tls)?)?,
+            None => server.bind(&config.bind)?,
+        };
+        let addrs = server.addrs();
+        let server = server.run();
+        log::info!("Server::build listening on {:?}", addrs);
+
+        Ok(RunningServer {
+            shutdown: ShutdownHandle {
+                server: server.handle(),
+            },
+            server,
+            addrs,
+            registry,
+        })
+    }
+}
+
+/// A bound server. Awaiting it serves until it is shut down, by a
+/// [`ShutdownHandle`] or a termination signal, and then saves every room
+/// still in play to the store.
+pub struct RunningServer {
+    server: Server,
+    shutdown: ShutdownHandle,
+    addrs: Vec<SocketAddr>,
+    registry: SharedRegistry,
+}
+
+impl RunningServer {
+    /// The addresses actually bound; useful after binding port 0.
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+}
+
+impl IntoFuture for RunningServer {
+    type Output = io::Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = io::Result<()>> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            let result = self.server.await;
+            log::info!("Server::stopped, saving rooms");
+            self.registry.lock().unwrap().persist_all();
+            result
+        })
+    }
+}
+
+/// Stops a [`RunningServer`] from anywhere.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    server: ServerHandle,
+}
+
+impl ShutdownHandle {
+    /// Stop accepting connections and give open ones the configured
+    /// `shutdown_timeout` to finish. Returns once the listener is down.
+    pub async fn shutdown(&self) {
+        self.server.stop(true).await;
+    }
+}
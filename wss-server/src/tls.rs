@@ -0,0 +1,215 @@
+//! TLS termination with rustls, so clients can connect to `wss://` directly.
+
+use std::io;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    Error, InconsistentKeys, ServerConfig,
+};
+
+use crate::config::TlsConfig;
+
+/// rustls settings serving the certificate and key `tls` points at. With a
+/// `reload_interval` the files are checked that often and a changed
+/// certificate is served to new connections without a restart.
+pub fn server_config(tls: &TlsConfig) -> io::Result<ServerConfig> {
+    let provider = Arc::new(ring::default_provider());
+    let resolver = Arc::new(CertResolver::load(tls.clone(), provider.clone())?);
+    if let Some(secs) = tls.reload_interval {
+        resolver.watch(Duration::from_secs(secs));
+    }
+    Ok(ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver))
+}
+
+/// Hands every handshake the most recently loaded certificate.
+#[derive(Debug)]
+struct CertResolver {
+    tls: TlsConfig,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+    /// When the files had last changed as of the current certificate.
+    modified: RwLock<Option<SystemTime>>,
+}
+
+impl CertResolver {
+    fn load(tls: TlsConfig, provider: Arc<CryptoProvider>) -> io::Result<Self> {
+        let modified = modified(&tls);
+        let key = certified_key(&tls, &provider)?;
+        log::info!("Tls::load cert={}", tls.cert.display());
+        Ok(Self {
+            tls,
+            provider,
+            current: RwLock::new(Arc::new(key)),
+            modified: RwLock::new(modified),
+        })
+    }
+
+    /// Load the files again if they changed since the last load. A broken
+    /// replacement is logged and the old certificate stays in use.
+    fn reload_if_changed(&self) {
+        let modified = modified(&self.tls);
+        if modified == *self.modified.read().unwrap() {
+            return;
+        }
+        match certified_key(&self.tls, &self.provider) {
+            Ok(key) => {
+                log::info!("Tls::reload cert={}", self.tls.cert.display());
+                *self.current.write().unwrap() = Arc::new(key);
+                *self.modified.write().unwrap() = modified;
+            }
+            Err(e) => log::warn!("Tls::reload failed, keeping the old certificate: {}", e),
+        }
+    }
+
+    /// Check the files every `interval` for as long as the server uses
+    /// this resolver.
+    fn watch(self: &Arc<Self>, interval: Duration) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            log::warn!("Tls::watch no runtime, certificate reload disabled");
+            return;
+        };
+        let resolver = Arc::downgrade(self);
+        runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(resolver) = resolver.upgrade() else {
+                    break;
+                };
+                resolver.reload_if_changed();
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+/// The later of the two files' modification times.
+fn modified(tls: &TlsConfig) -> Option<SystemTime> {
+    [&tls.cert, &tls.key]
+        .into_iter()
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+fn certified_key(tls: &TlsConfig, provider: &CryptoProvider) -> io::Result<CertifiedKey> {
+    let invalid = |path: &std::path::Path, e: &dyn std::fmt::Display| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    };
+    let certs = CertificateDer::pem_file_iter(&tls.cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| invalid(&tls.cert, &e))?;
+    if certs.is_empty() {
+        return Err(invalid(&tls.cert, &"no certificate found"));
+    }
+    let key = PrivateKeyDer::from_pem_file(&tls.key).map_err(|e| invalid(&tls.key, &e))?;
+    let key = provider
+        .key_provider
+        .load_private_key(key)
+        .map_err(|e| invalid(&tls.key, &e))?;
+    let certified = CertifiedKey::new(certs, key);
+    // A key whose public half the provider cannot report is let through,
+    // as rustls itself does; a key for another certificate is not.
+    match certified.keys_match() {
+        Ok(()) | Err(Error::InconsistentKeys(InconsistentKeys::Unknown)) => Ok(certified),
+        Err(e) => Err(invalid(&tls.key, &e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    fn served(resolver: &CertResolver) -> Vec<u8> {
+        resolver.current.read().unwrap().cert[0].to_vec()
+    }
+
+    #[test]
+    fn missing_or_garbled_files_are_refused() {
+        let tls = TlsConfig {
+            cert: fixture("localhost.crt"),
+            key: fixture("nope.key"),
+            reload_interval: None,
+        };
+        assert!(server_config(&tls).is_err());
+        let tls = TlsConfig {
+            key: fixture("localhost.crt"),
+            ..tls
+        };
+        assert!(server_config(&tls).is_err());
+    }
+
+    #[test]
+    fn changed_certificate_is_picked_up() {
+        let dir = std::env::temp_dir().join(format!("wss-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tls = TlsConfig {
+            cert: dir.join("server.crt"),
+            key: dir.join("server.key"),
+            reload_interval: None,
+        };
+        std::fs::copy(fixture("localhost.crt"), &tls.cert).unwrap();
+        std::fs::copy(fixture("localhost.key"), &tls.key).unwrap();
+        let resolver = CertResolver::load(tls.clone(), Arc::new(ring::default_provider())).unwrap();
+        let first = served(&resolver);
+
+        std::fs::write(&tls.cert, "not a certificate").unwrap();
+        resolver.reload_if_changed();
+        assert_eq!(served(&resolver), first, "a broken file keeps the old one");
+
+        std::fs::copy(fixture("localhost-next.crt"), &tls.cert).unwrap();
+        std::fs::copy(fixture("localhost-next.key"), &tls.key).unwrap();
+        resolver.reload_if_changed();
+        assert_ne!(served(&resolver), first);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn certificate_without_its_key_is_not_served() {
+        let dir = std::env::temp_dir().join(format!("wss-tls-mismatch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tls = TlsConfig {
+            cert: dir.join("server.crt"),
+            key: dir.join("server.key"),
+            reload_interval: None,
+        };
+        std::fs::copy(fixture("localhost.crt"), &tls.cert).unwrap();
+        std::fs::copy(fixture("localhost.key"), &tls.key).unwrap();
+        let resolver = CertResolver::load(tls.clone(), Arc::new(ring::default_provider())).unwrap();
+        let first = served(&resolver);
+
+        std::fs::copy(fixture("localhost-next.crt"), &tls.cert).unwrap();
+        resolver.reload_if_changed();
+        assert_eq!(served(&resolver), first, "the new cert with the old key");
+
+        let tls = TlsConfig {
+            key: fixture("localhost-next.key"),
+            cert: fixture("localhost.crt"),
+            ..tls
+        };
+        assert!(server_config(&tls).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
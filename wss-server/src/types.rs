@@ -3,19 +3,27 @@ use actix::Addr;
 use std::collections::HashMap;
 
 pub type PlayerId = String;
+pub type RoomId = String;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum Role {
     Werewolf,
     Seer,
     Villager,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum Phase {
     Lobby,
+    /// Mental-poker setup: key aggregation, shuffle, card picks, decryption
+    /// and role reveals.
+    Dealing,
     Night,
     Day,
+    /// Mental-poker game end: the winner is known and every player proves
+    /// the card they were dealt before the roles are announced.
+    Reveal,
     GameOver,
 }
 
@@ -27,6 +35,21 @@ pub struct Player {
     pub is_ready: bool,
     pub is_alive: bool,
     pub addr: Option<Addr<WsClient>>,
+    /// Secret handed out on `join`; presenting it in `resume` rebinds a new
+    /// connection to this player.
+    pub session_token: String,
+    /// Bumped on every disconnect and resume so a stale grace timer can
+    /// tell the player has come back (or left again) since it was armed.
+    pub connection_epoch: u64,
 }
 
-pub type VoteMap = HashMap<PlayerId, PlayerId>;
+/// A read-only connection watching a room.
+#[derive(Debug)]
+pub struct Spectator {
+    pub addr: Addr<WsClient>,
+    /// Also receives the delayed `spectatorView` frames.
+    pub full_view: bool,
+}
+
+/// Each voter's ballot: the player they vote against, or `None` to abstain.
+pub type VoteMap = HashMap<PlayerId, Option<PlayerId>>;
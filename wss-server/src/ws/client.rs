@@ -1,22 +1,36 @@
-use crate::utils::{aggregate_public_keys, verify_card_message, verify_shuffle};
 use crate::{
-    room::room::{DecryptCtx, Room, SharedRoom},
-    types::PlayerId,
+    message::{
+        Ack, AckStatus, ErrorCode, Joined, KeyChallenge, NightAck, Reason, RoomList, RoomRef,
+        ServerEvent,
+    },
+    prover::{KeyClaim, SharedBackend},
+    room::registry::SharedRegistry,
+    room::room::SharedRoom,
+    types::{PlayerId, RoomId},
 };
 use actix::AsyncContext;
 use actix::{Actor, ActorContext, Handler, Message, StreamHandler};
 use actix_web::rt::task;
 use actix_web_actors::ws;
-use std::collections::VecDeque;
 
 pub struct WsClient {
     pub id: PlayerId,
-    room: SharedRoom,
+    registry: SharedRegistry,
+    backend: SharedBackend,
+    room: Option<SharedRoom>,
+    /// `room` is only being watched, not played in.
+    spectating: bool,
 }
 
 impl WsClient {
-    pub fn new(id: PlayerId, room: SharedRoom) -> Self {
-        Self { id, room }
+    pub fn new(id: PlayerId, registry: SharedRegistry, backend: SharedBackend) -> Self {
+        Self {
+            id,
+            registry,
+            backend,
+            room: None,
+            spectating: false,
+        }
     }
 }
 
@@ -31,80 +45,95 @@ impl Handler<ServerText> for WsClient {
     }
 }
 
+/// The room gave this client's seat away, e.g. after a kick; it is free to
+/// join another room.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Unseated;
+
+impl Handler<Unseated> for WsClient {
+    type Result = ();
+    fn handle(&mut self, _msg: Unseated, _ctx: &mut Self::Context) {
+        log::info!("Client {} unseated", self.id);
+        self.room = None;
+    }
+}
+
 impl Actor for WsClient {
     type Context = ws::WebsocketContext<Self>;
-}
 
-fn send_need_decrypt(room: &Room, requester: &PlayerId, helper: &PlayerId) {
-    if let Some(addr) = room.players.get(helper).and_then(|p| p.addr.as_ref()) {
-        if let Some(ctx) = room.decrypt_ctx.get(requester) {
-            let frame = serde_json::json!({
-            "type": 1,
-            "target": "needDecrypt",
-            "arguments": [{
-               "for":   requester,
-               "card":  ctx.card_index,
-               "cipher": ctx.current_cipher
-            }]
-            })
-            .to_string();
-
-            addr.do_send(crate::ws::client::ServerText(frame));
-            println!("→ needDecrypt to {} for {}", helper, requester);
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        if let Some(room) = &self.room {
+            let mut room = room.lock().unwrap();
+            if self.spectating {
+                room.remove_spectator(&self.id);
+            } else {
+                room.disconnect(&self.id, &ctx.address());
+            }
         }
     }
 }
 
-fn send_partial_ready(room: &Room, requester: &PlayerId, partial: &[String; 2], component: &str) {
-    if let Some(addr) = room.players.get(requester).and_then(|p| p.addr.as_ref()) {
-        let frame = serde_json::json!({
-        "type": 1,
-        "target": "partialReady",
-        "arguments": [{
-        "card":  room.decrypt_ctx[requester].card_index,
-        "partial":  partial,
-        "component": component,
-        "components": room.decrypt_ctx[requester].components
-        }]
-        })
-        .to_string();
-        addr.do_send(crate::ws::client::ServerText(frame));
-    }
+fn send_role_rejected(room: &SharedRoom, player: &PlayerId, reason: &str) {
+    room.lock().unwrap().send_to(
+        player,
+        &ServerEvent::RoleRejected(Reason {
+            reason: reason.to_string(),
+        }),
+    );
 }
 
-fn send_all_parts_ready(room: &Room, requester: &PlayerId) {
-    if let Some(addr) = room.players.get(requester).and_then(|p| p.addr.as_ref()) {
-        let frame = serde_json::json!({
-        "type": 1,
-        "target": "allPartsReady",
-        "arguments": [{
-        "card": room.decrypt_ctx[requester].card_index
-        }]
-        })
-        .to_string();
-        addr.do_send(crate::ws::client::ServerText(frame));
-    }
+/// Mental-poker dealing has started: aggregate the registered keys on a
+/// worker thread, outside the room lock, then hand the deck to the first
+/// shuffler.
+fn spawn_aggregation(room: &SharedRoom, backend: &SharedBackend) {
+    let Some(pks) = room.lock().unwrap().begin_aggregation() else {
+        return;
+    };
+    log::info!("Client::Aggregating {} public keys...", pks.len());
+    let room = room.clone();
+    let backend = backend.clone();
+    task::spawn_blocking(move || {
+        let result = backend
+            .aggregate_public_keys(&pks)
+            .map_err(|e| format!("{e:#}"));
+        room.lock().unwrap().finish_aggregation(result);
+    });
 }
 
 fn send_night_ack(room: &SharedRoom, player: &PlayerId, ok: bool, reason: &str) {
-    if let Some(addr) = room
-        .lock()
-        .unwrap()
-        .players
-        .get(player)
-        .and_then(|p| p.addr.as_ref())
-    {
-        let frame = serde_json::json!({
-            "type": 1,
-            "target": "nightAck",
-            "arguments": [{
-                "status": if ok { "ok" } else { "rejected" },
-                "reason": reason
-            }]
-        })
-        .to_string();
-
-        addr.do_send(crate::ws::client::ServerText(frame));
+    room.lock().unwrap().send_to(
+        player,
+        &ServerEvent::NightAck(NightAck {
+            status: if ok {
+                AckStatus::Ok
+            } else {
+                AckStatus::Rejected
+            },
+            reason: reason.to_string(),
+        }),
+    );
+}
+
+/// Answer a frame the server will not act on with an `error` frame.
+fn reply_error(
+    ctx: &mut ws::WebsocketContext<WsClient>,
+    code: ErrorCode,
+    target: &str,
+    message: &str,
+) {
+    log::warn!("✘ {target} refused ({code:?}): {message}");
+    ctx.text(ServerEvent::error(code, target, message).to_frame());
+}
+
+/// Forget a room in the registry once its game has ended.
+fn release_if_finished(registry: &SharedRegistry, room: &SharedRoom) {
+    let finished = {
+        let room = room.lock().unwrap();
+        room.is_finished().then(|| room.id.clone())
+    };
+    if let Some(id) = finished {
+        registry.lock().unwrap().remove(&id);
     }
 }
 
@@ -112,286 +141,421 @@ impl WsClient {
     fn handle_text(&mut self, raw: String, ctx: &mut ws::WebsocketContext<Self>) {
         use crate::message::{to_client_event, ClientEvent, Incoming};
 
-        match serde_json::from_str::<Incoming>(&raw) {
-            Ok(inc) => match to_client_event(inc) {
-                Ok(ClientEvent::Join { name }) => {
-                    let addr = ctx.address();
-                    self.room
-                        .lock()
-                        .unwrap()
-                        .add_player(self.id.clone(), name, addr);
-                }
-                Ok(ClientEvent::Ready(flag)) => {
-                    self.room.lock().unwrap().set_ready(self.id.clone(), flag);
+        let inc = match serde_json::from_str::<Incoming>(&raw) {
+            Ok(inc) => inc,
+            Err(e) => {
+                let message = format!("malformed frame: {e}");
+                return reply_error(ctx, ErrorCode::MalformedJson, "", &message);
+            }
+        };
+        let target = inc.target.clone();
+        match to_client_event(inc) {
+            Ok(ClientEvent::CreateRoom) => {
+                let (room_id, _) = self.registry.lock().unwrap().create();
+                ctx.text(ServerEvent::RoomCreated(RoomRef { room_id }).to_frame());
+            }
+            Ok(ClientEvent::ListRooms) => {
+                let rooms = self.registry.lock().unwrap().list();
+                ctx.text(ServerEvent::Rooms(RoomList { rooms }).to_frame());
+            }
+            Ok(ClientEvent::Join { name, room_id }) => self.join_room(name, room_id, ctx),
+            Ok(ClientEvent::Resume { token }) => self.resume(token, ctx),
+            Ok(ClientEvent::Spectate { room_id, full_view }) => {
+                self.spectate(room_id, full_view, ctx)
+            }
+            Ok(ClientEvent::RawUnknown) => {
+                let message = format!("unknown target {target:?}");
+                reply_error(ctx, ErrorCode::UnknownTarget, &target, &message);
+            }
+            Ok(_) if self.spectating => {
+                reply_error(ctx, ErrorCode::NotAllowed, &target, "spectators cannot act")
+            }
+            Ok(evt) => match self.room.clone() {
+                Some(shared) => {
+                    self.handle_room_event(evt, &target, &shared, ctx);
+                    release_if_finished(&self.registry, &shared);
                 }
-                Ok(ClientEvent::NightAction {
-                    action,
-                    target,
-                    proof,
-                    public_inputs,
-                }) => {
-                    let room = self.room.clone();
-                    let me_id = self.id.clone();
-
-                    // Heavy ZK proof check offloaded to a thread pool
-                    task::spawn_blocking(move || {
-                        match verify_card_message(&public_inputs, &proof) {
-                            Ok(true) => {
-                                println!("✔ nightAction proof valid for {me_id}");
-
-                                let mut room_guard = room.lock().unwrap();
-                                println!(
-                                    "nightAction from {me_id} → action: {action}, target: {target}"
-                                );
-                                room_guard.night_action(me_id.clone(), action, target);
-                                send_night_ack(&room, &me_id, true, "");
-                            }
-                            Ok(false) => {
-                                eprintln!("✘ invalid nightAction proof from {me_id}");
-                                send_night_ack(&room, &me_id, false, "invalid proof");
-                            }
-                            Err(e) => {
-                                eprintln!("verify_card_message failed for {me_id}: {e:#}");
-                                send_night_ack(&room, &me_id, false, "verifier error");
-                            }
-                        }
-                    });
+                None => reply_error(ctx, ErrorCode::NotInRoom, &target, "join a room first"),
+            },
+            Err(e) => reply_error(ctx, ErrorCode::BadPayload, &target, &e),
+        }
+    }
+
+    fn join_room(
+        &mut self,
+        name: String,
+        room_id: Option<RoomId>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        if let Some(current) = &self.room {
+            if !current.lock().unwrap().is_finished() {
+                let message = "already seated in a room";
+                return reply_error(ctx, ErrorCode::AlreadySeated, "join", message);
+            }
+        }
+
+        let found = {
+            let mut registry = self.registry.lock().unwrap();
+            match &room_id {
+                Some(id) => registry.get(id).map(|room| (id.clone(), room)),
+                None => Some(registry.find_open()),
+            }
+        };
+
+        let joined = match found {
+            Some((id, room)) => room
+                .lock()
+                .unwrap()
+                .add_player(self.id.clone(), name, ctx.address())
+                .map(|token| (id, token, room.clone())),
+            None => Err(format!("no room with id {}", room_id.unwrap_or_default())),
+        };
+
+        match joined {
+            Ok((id, token, room)) => {
+                self.room = Some(room);
+                self.spectating = false;
+                let joined = ServerEvent::Joined(Joined {
+                    room_id: id,
+                    player_id: self.id.clone(),
+                    session_token: token,
+                });
+                ctx.text(joined.to_frame());
+            }
+            Err(reason) => {
+                log::warn!("join from {} failed: {}", self.id, reason);
+                ctx.text(ServerEvent::JoinFailed(Reason { reason }).to_frame());
+            }
+        }
+    }
+
+    /// Watch `room_id` read-only; `full_view` adds the delayed view with
+    /// every role.
+    fn spectate(&mut self, room_id: RoomId, full_view: bool, ctx: &mut ws::WebsocketContext<Self>) {
+        if let Some(current) = &self.room {
+            if !current.lock().unwrap().is_finished() {
+                let message = "already in a room";
+                return reply_error(ctx, ErrorCode::AlreadySeated, "spectate", message);
+            }
+        }
+
+        let Some(room) = self.registry.lock().unwrap().get(&room_id) else {
+            let message = format!("no room with id {room_id}");
+            return reply_error(ctx, ErrorCode::BadPayload, "spectate", &message);
+        };
+        room.lock()
+            .unwrap()
+            .add_spectator(self.id.clone(), ctx.address(), full_view);
+        self.room = Some(room);
+        self.spectating = true;
+    }
+
+    /// Take over the seat of a player who lost their connection.
+    fn resume(&mut self, token: String, ctx: &mut ws::WebsocketContext<Self>) {
+        if let Some(current) = &self.room {
+            if !current.lock().unwrap().is_finished() {
+                let message = "already seated in a room";
+                return reply_error(ctx, ErrorCode::AlreadySeated, "resume", message);
+            }
+        }
+
+        let found = self.registry.lock().unwrap().find_session(&token);
+        let resumed = match found {
+            Some(room) => room
+                .lock()
+                .unwrap()
+                .resume(&token, ctx.address())
+                .map(|id| (id, room.clone())),
+            None => Err("unknown session token".to_string()),
+        };
+
+        match resumed {
+            Ok((id, room)) => {
+                log::info!("Client {} resumed as {}", self.id, id);
+                self.id = id;
+                self.room = Some(room);
+                self.spectating = false;
+            }
+            Err(reason) => {
+                log::warn!("resume from {} failed: {}", self.id, reason);
+                ctx.text(ServerEvent::ResumeFailed(Reason { reason }).to_frame());
+            }
+        }
+    }
+
+    fn handle_room_event(
+        &mut self,
+        evt: crate::message::ClientEvent,
+        target: &str,
+        shared: &SharedRoom,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        use crate::message::ClientEvent;
+
+        match evt {
+            ClientEvent::Configure(update) => {
+                let outcome = shared.lock().unwrap().configure(&self.id, update);
+                if let Err(reason) = outcome {
+                    reply_error(ctx, ErrorCode::NotAllowed, target, &reason);
                 }
-                Ok(ClientEvent::Vote { target }) => {
-                    self.room.lock().unwrap().vote(self.id.clone(), target);
+                spawn_aggregation(shared, &self.backend);
+            }
+            ClientEvent::Ready(flag) => {
+                shared.lock().unwrap().set_ready(self.id.clone(), flag);
+                spawn_aggregation(shared, &self.backend);
+            }
+            ClientEvent::RetryAggregation => {
+                let outcome = shared.lock().unwrap().retry_aggregation(&self.id);
+                match outcome {
+                    Ok(()) => spawn_aggregation(shared, &self.backend),
+                    Err(reason) => reply_error(ctx, ErrorCode::NotAllowed, target, &reason),
                 }
-                Ok(ClientEvent::Chat { text }) => {
-                    self.room.lock().unwrap().chat(self.id.clone(), text);
+            }
+            ClientEvent::NightAction { action, target } => {
+                log::info!(
+                    "nightAction from {} → action: {action}, target: {target}",
+                    self.id
+                );
+                let outcome = shared
+                    .lock()
+                    .unwrap()
+                    .night_action(self.id.clone(), action, target);
+                match outcome {
+                    Ok(()) => send_night_ack(shared, &self.id, true, ""),
+                    Err(reason) => {
+                        log::warn!("✘ nightAction from {} rejected: {reason}", self.id);
+                        send_night_ack(shared, &self.id, false, &reason);
+                    }
                 }
-                Ok(ClientEvent::RegisterPublicKey { public_key }) => {
-                    let mut room = self.room.lock().unwrap();
-                    room.register_public_key(&self.id, public_key.clone());
-
-                    let ack = ServerText(
-                        serde_json::to_string(&serde_json::json!({
-                            "type":1,
-                            "target":"publicKeyRegistered",
-                            "arguments":[{ "status":"ok" }]
-                        }))
-                        .unwrap(),
-                    );
-                    ctx.text(ack.0);
-
-                    let num_players = room.players.len();
-                    let num_keys = room.public_keys.len();
-
-                    if num_players == 4 && num_keys == 4 {
-                        print!("Client::All players registered their public keys.");
-                        println!("Client::Aggregating public keys...");
-                        let agg_pk =
-                            aggregate_public_keys(&room).expect("failed to aggregate public keys");
-
-                        room.agg_pk = agg_pk.clone();
-                        println!("Client::Public keys aggregated. agg_pk: {:?}", agg_pk);
-                        println!("Client::Starting shuffle...");
-                        room.initiate_shuffle();
+                release_if_finished(&self.registry, shared);
+            }
+            ClientEvent::Vote { target } => {
+                shared.lock().unwrap().vote(self.id.clone(), Some(target));
+            }
+            ClientEvent::Abstain => {
+                shared.lock().unwrap().vote(self.id.clone(), None);
+            }
+            ClientEvent::RetractVote => {
+                shared.lock().unwrap().retract_vote(self.id.clone());
+            }
+            ClientEvent::Chat { text, channel } => {
+                shared.lock().unwrap().chat(self.id.clone(), text, channel);
+            }
+            ClientEvent::RegisterPublicKey { public_key } => {
+                let claim = shared.lock().unwrap().key_challenge(&self.id, public_key);
+                match claim {
+                    Ok(KeyClaim { nonce, .. }) => {
+                        ctx.text(ServerEvent::KeyChallenge(KeyChallenge { nonce }).to_frame())
                     }
+                    Err(reason) => reply_error(ctx, ErrorCode::NotAllowed, target, &reason),
                 }
-                Ok(ClientEvent::ShuffleDone {
-                    encrypted_deck,
-                    public_inputs,
-                    proof,
-                }) => {
-                    println!(
-                        "shuffleDone from {} → deck rows {}, inputs {}, proof head {}…",
-                        self.id,
-                        encrypted_deck.len(),
-                        public_inputs.len(),
-                        &proof[..10.min(proof.len())],
-                    );
-
-                    let room = self.room.clone();
-                    let my_id = self.id.clone();
-                    let inputs = public_inputs.clone();
-                    let prf = proof.clone();
-                    let deck = encrypted_deck.clone();
-
-                    task::spawn_blocking(move || match verify_shuffle(&inputs, &prf) {
-                        Ok(true) => {
-                            println!("✔ proof valid for player {my_id}");
-
-                            let mut room = room.lock().unwrap();
-
-                            if room.shuffle_order.get(room.shuffle_index) != Some(&my_id) {
-                                eprintln!("shuffleDone from out-of-turn player {my_id}");
-                                return;
-                            }
-
-                            room.deck_state = deck;
-                            room.shuffle_index += 1;
-
-                            let make_frame = |target: &str, room: &Room| {
-                                serde_json::json!({
-                                    "type": 1,
-                                    "target": target,
-                                    "arguments": [{
-                                        "agg_pk": room.agg_pk,
-                                        "deck":   room.deck_state
-                                    }]
-                                })
-                                .to_string()
-                            };
-
-                            if room.shuffle_index < room.shuffle_order.len() {
-                                let next_id = &room.shuffle_order[room.shuffle_index];
-                                if let Some(addr) =
-                                    room.players.get(next_id).and_then(|p| p.addr.as_ref())
-                                {
-                                    println!("→ startShuffle to {next_id}");
-                                    addr.do_send(crate::ws::client::ServerText(make_frame(
-                                        "startShuffle",
-                                        &room,
-                                    )));
-                                }
-                            } else {
-                                let frame = serde_json::json!({
-                                    "type": 1,
-                                    "target": "shuffleComplete",
-                                    "arguments": [{
-                                        "deck": room.deck_state
-                                    }]
-                                })
-                                .to_string();
-                                println!("✓ shuffle phase complete");
-                                for addr in room.players.values().filter_map(|p| p.addr.as_ref()) {
-                                    addr.do_send(crate::ws::client::ServerText(frame.clone()));
-                                }
-                            }
-                        }
-
+            }
+            ClientEvent::SignChallenge { signature } => {
+                let claim = shared.lock().unwrap().key_claim(&self.id);
+                let claim = match claim {
+                    Ok(claim) => claim,
+                    Err(reason) => return reply_error(ctx, ErrorCode::OutOfOrder, target, &reason),
+                };
+
+                let room = shared.clone();
+                let backend = self.backend.clone();
+                let me_id = self.id.clone();
+
+                task::spawn_blocking(move || {
+                    let verified = backend.verify_key_signature(&claim, &signature);
+                    let mut locked = room.lock().unwrap();
+                    let outcome = match verified {
+                        Ok(true) => locked.accept_key(&me_id, claim),
                         Ok(false) => {
-                            eprintln!("✘ proof INVALID from player {my_id}");
-                            if let Some(addr) = room
-                                .lock()
-                                .unwrap()
-                                .players
-                                .get(&my_id)
-                                .and_then(|p| p.addr.as_ref())
-                            {
-                                let rej = serde_json::json!({
-                                    "type":1,
-                                    "target":"shuffleRejected",
-                                    "arguments":[{ "reason":"invalid proof" }]
-                                })
-                                .to_string();
-                                addr.do_send(crate::ws::client::ServerText(rej));
-                            }
+                            locked.reject_key(&me_id, &claim);
+                            Err("invalid signature; register the key again".to_string())
+                        }
+                        Err(e) => {
+                            let message = format!("verifier error: {e:#}");
+                            return locked.reject(
+                                &me_id,
+                                ErrorCode::VerifierError,
+                                "signChallenge",
+                                &message,
+                            );
+                        }
+                    };
+                    match outcome {
+                        Ok(()) => {
+                            let ack = ServerEvent::PublicKeyRegistered(Ack {
+                                status: AckStatus::Ok,
+                            });
+                            locked.send_to(&me_id, &ack);
+                            locked.try_start();
+                            drop(locked);
+                            spawn_aggregation(&room, &backend);
+                        }
+                        Err(reason) => {
+                            log::warn!("✘ signChallenge from {me_id} rejected: {reason}");
+                            locked.reject(&me_id, ErrorCode::NotAllowed, "signChallenge", &reason);
                         }
+                    }
+                });
+            }
+            ClientEvent::RevealRole { role, proof } => {
+                let claim = shared.lock().unwrap().card_claim(&self.id, role);
+                let claim = match claim {
+                    Ok(claim) => claim,
+                    Err(reason) => return send_role_rejected(shared, &self.id, &reason),
+                };
+
+                let room = shared.clone();
+                let backend = self.backend.clone();
+                let me_id = self.id.clone();
+
+                task::spawn_blocking(move || {
+                    let verified = backend.verify_card_message(&claim, &proof);
+                    let outcome = match verified {
+                        Ok(true) => room.lock().unwrap().reveal_role(&me_id, claim),
+                        Ok(false) => Err("invalid proof".to_string()),
+                        Err(e) => Err(format!("verifier error: {e:#}")),
+                    };
+                    if let Err(reason) = outcome {
+                        log::warn!("✘ revealRole from {me_id} rejected: {reason}");
+                        send_role_rejected(&room, &me_id, &reason);
+                    }
+                });
+            }
+            ClientEvent::ShuffleDone {
+                encrypted_deck,
+                proof,
+            } => {
+                log::info!(
+                    "shuffleDone from {} → deck rows {}, proof head {}…",
+                    self.id,
+                    encrypted_deck.len(),
+                    proof.chars().take(10).collect::<String>(),
+                );
+                let claim = shared
+                    .lock()
+                    .unwrap()
+                    .shuffle_claim(&self.id, encrypted_deck);
+                let claim = match claim {
+                    Ok(claim) => claim,
+                    Err(reason) => {
+                        return reply_error(ctx, ErrorCode::NotYourTurn, target, &reason)
+                    }
+                };
 
-                        Err(e) => eprintln!("verify call failed for {my_id}: {e:#}"),
-                    });
-                }
-                Ok(ClientEvent::PickCard { card }) => {
-                    println!("Player {} is attempting to pick card {}", self.id, card);
-                    let mut room = self.room.lock().unwrap();
-
-                    if room.taken_cards.values().any(|&c| c == card) {
-                        let deny = serde_json::json!({
-                            "type":1,
-                            "target":"cardTaken",
-                            "arguments":[{ "status":"denied", "card": card }]
-                        })
-                        .to_string();
-                        ctx.text(deny); // ← sent only to the player who picked
-                    } else {
-                        room.taken_cards.insert(self.id.clone(), card);
-
-                        let ok = serde_json::json!({
-                            "type":1,
-                            "target":"cardTaken",
-                            "arguments":[{ "status":"ok", "card": card }]
-                        })
-                        .to_string();
-
-                        ctx.text(ok); // ← send only to this client, no more broadcast
-
-                        // (no for-loop broadcast here)
-
-                        if room.taken_cards.len() == 4 {
-                            println!("All cards claimed – setting up decrypt queues");
-
-                            let taken_cards: Vec<(PlayerId, usize)> = room
-                                .taken_cards
-                                .iter()
-                                .map(|(player_id, &idx)| (player_id.clone(), idx))
-                                .collect();
-
-                            for (player_id, idx) in taken_cards {
-                                let helpers: VecDeque<PlayerId> = room
-                                    .shuffle_order
-                                    .iter()
-                                    .filter(|pid| *pid != &player_id)
-                                    .cloned()
-                                    .collect();
-
-                                let cipher = room.deck_state[idx].clone();
-
-                                room.decrypt_ctx.insert(
-                                    player_id.clone(),
-                                    DecryptCtx {
-                                        helpers,
-                                        current_cipher: cipher,
-                                        components: Vec::new(),
-                                        card_index: idx,
-                                    },
-                                );
-                            }
+                let room = shared.clone();
+                let my_id = self.id.clone();
+                let backend = self.backend.clone();
 
-                            for (player_id, ctx) in &room.decrypt_ctx {
-                                if let Some(first_helper) = ctx.helpers.front() {
-                                    send_need_decrypt(&room, player_id, first_helper);
-                                }
+                task::spawn_blocking(move || {
+                    let verified = backend.verify_shuffle(&claim, &proof);
+                    let mut room = room.lock().unwrap();
+                    match verified {
+                        Ok(true) => {
+                            log::info!("✔ proof valid for player {my_id}");
+                            if let Err(reason) = room.accept_shuffle(&my_id, claim) {
+                                room.reject(&my_id, ErrorCode::NotYourTurn, "shuffleDone", &reason);
                             }
                         }
+                        Ok(false) => {
+                            log::warn!("✘ proof INVALID from player {my_id}");
+                            room.reject_shuffle(&my_id, "invalid proof");
+                        }
+                        Err(e) => room.reject(
+                            &my_id,
+                            ErrorCode::VerifierError,
+                            "shuffleDone",
+                            &format!("verifier error: {e:#}"),
+                        ),
                     }
-                }
-                Ok(ClientEvent::DecryptCard {
-                    for_player,
-                    card,
+                });
+            }
+            ClientEvent::PickCard { card } => {
+                shared.lock().unwrap().pick_card(&self.id, card);
+            }
+            ClientEvent::DecryptCard {
+                for_player,
+                card: _,
+                partial,
+                component,
+                proof,
+            } => {
+                let claim = shared.lock().unwrap().decryption_claim(
+                    &self.id,
+                    &for_player,
                     partial,
                     component,
-                }) => {
-                    let mut room = self.room.lock().unwrap();
-
-                    if let Some(ctx) = room.decrypt_ctx.get_mut(&for_player) {
-                        match ctx.helpers.front() {
-                            Some(expected) if expected == &self.id => {}
-                            _ => {
-                                eprintln!("decryptCard out-of-order from {}", self.id);
-                                return;
+                );
+                let claim = match claim {
+                    Ok(claim) => claim,
+                    Err(reason) => return reply_error(ctx, ErrorCode::OutOfOrder, target, &reason),
+                };
+
+                let room = shared.clone();
+                let backend = self.backend.clone();
+                let me_id = self.id.clone();
+
+                task::spawn_blocking(move || {
+                    let verified = backend.verify_partial_decryption(&claim, &proof);
+                    let mut room = room.lock().unwrap();
+                    match verified {
+                        Ok(true) => {
+                            if let Err(reason) =
+                                room.accept_partial_decryption(&me_id, &for_player, claim)
+                            {
+                                room.reject(&me_id, ErrorCode::OutOfOrder, "decryptCard", &reason);
                             }
                         }
-
-                        ctx.components.push(component.clone());
-                        ctx.current_cipher = partial.clone();
-                        ctx.helpers.pop_front();
-
-                        if let Some(next_helper) = ctx.helpers.front() {
-                            let next_helper_clone = next_helper.clone();
-                            let for_player_clone = for_player.clone();
-                            drop(room);
-                            send_need_decrypt(
-                                &self.room.lock().unwrap(),
-                                &for_player_clone,
-                                &next_helper_clone,
+                        Ok(false) => {
+                            room.flag_helper(&me_id, &for_player, "invalid decryption proof")
+                        }
+                        Err(e) => room.reject(
+                            &me_id,
+                            ErrorCode::VerifierError,
+                            "decryptCard",
+                            &format!("verifier error: {e:#}"),
+                        ),
+                    }
+                });
+            }
+            ClientEvent::RevealCard { role, proof } => {
+                let claim = shared.lock().unwrap().reveal_claim(&self.id, role);
+                let claim = match claim {
+                    Ok(claim) => claim,
+                    Err(reason) => return reply_error(ctx, ErrorCode::WrongPhase, target, &reason),
+                };
+
+                let room = shared.clone();
+                let registry = self.registry.clone();
+                let backend = self.backend.clone();
+                let me_id = self.id.clone();
+
+                task::spawn_blocking(move || {
+                    let verified = backend.verify_card_reveal(&claim, &proof);
+                    let outcome = match verified {
+                        Ok(true) => room.lock().unwrap().accept_reveal(&me_id, claim),
+                        Ok(false) => Err("invalid card proof".to_string()),
+                        Err(e) => {
+                            let message = format!("verifier error: {e:#}");
+                            let room = room.lock().unwrap();
+                            return room.reject(
+                                &me_id,
+                                ErrorCode::VerifierError,
+                                "revealCard",
+                                &message,
                             );
-                        } else {
-                            send_partial_ready(&room, &for_player, &partial, &component);
-                            send_all_parts_ready(&room, &for_player);
                         }
+                    };
+                    if let Err(reason) = outcome {
+                        log::warn!("✘ revealCard from {me_id} rejected: {reason}");
+                        let room = room.lock().unwrap();
+                        room.reject(&me_id, ErrorCode::NotAllowed, "revealCard", &reason);
                     }
-                }
-                Ok(evt) => {
-                    println!("Unhandled event: {:?}", evt);
-                }
-                Err(e) => eprintln!("Bad payload: {:?}", e),
-            },
-            Err(e) => eprintln!("Malformed JSON: {:?}", e),
+                    release_if_finished(&registry, &room);
+                });
+            }
+            evt => {
+                log::info!("Unhandled event: {:?}", evt);
+            }
         }
     }
 }
@@ -402,7 +566,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsClient {
             Ok(ws::Message::Ping(payload)) => ctx.pong(&payload),
             Ok(ws::Message::Text(raw)) => self.handle_text(raw.to_string(), ctx),
             Ok(ws::Message::Close(reason)) => {
-                println!("Client {} disconnected: {:?}", self.id, reason);
+                log::info!("Client {} disconnected: {:?}", self.id, reason);
                 ctx.stop();
             }
             _ => {}
@@ -0,0 +1,126 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::{
+    task,
+    time::{timeout, Duration},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use url::Url;
+use wss_server::config::ServerConfig;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn spawn_server() -> (u16, task::JoinHandle<()>) {
+    let port = portpicker::pick_unused_port().unwrap();
+    let config = ServerConfig {
+        bind: format!("127.0.0.1:{port}"),
+        ..ServerConfig::default()
+    };
+    let server = wss_server::run_on(&config).await.unwrap();
+    let handle = task::spawn(async move {
+        server.await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    (port, handle)
+}
+
+/// Skip frames until the next `error` frame and return its payload.
+async fn next_error(sock: &mut Socket) -> Value {
+    loop {
+        let msg = timeout(Duration::from_secs(5), sock.next())
+            .await
+            .expect("no error frame within 5s")
+            .unwrap()
+            .unwrap();
+        if let Message::Text(txt) = msg {
+            let v: Value = serde_json::from_str(&txt).unwrap();
+            if v["target"] == "error" {
+                return v["arguments"][0].clone();
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn refused_frames_get_error_replies() {
+    // Format log arguments even without a logger, so a log line that
+    // mishandles client text fails here too.
+    log::set_max_level(log::LevelFilter::Info);
+    let (port, srv) = spawn_server().await;
+    let url = Url::parse(&format!("ws://127.0.0.1:{port}/ws")).unwrap();
+    let (mut sock, _) = connect_async(url).await.unwrap();
+
+    sock.send(Message::Text("not json".into())).await.unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(err["code"], "malformedJson");
+
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"dance","arguments":[]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(err["code"], "unknownTarget");
+    assert_eq!(err["target"], "dance");
+
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"vote","arguments":["x"]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(err["code"], "notInRoom");
+
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"join","arguments":[{}]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(err["code"], "badPayload");
+    assert_eq!(err["target"], "join");
+
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"join","arguments":[{"name":"P0"}]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"vote","arguments":["x"]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(err["code"], "wrongPhase");
+    assert_eq!(err["target"], "vote");
+
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"decryptCard","arguments":[{"for":"x","card":0,"partial":[1,2],"component":"c","proof":"p"}]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(err["code"], "badPayload");
+    assert_eq!(err["target"], "decryptCard");
+
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"shuffleDone","arguments":[{"encrypted_deck":[],"proof":"aééééééééé"}]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(
+        err["code"], "notYourTurn",
+        "a multi-byte proof is logged safely"
+    );
+
+    sock.send(Message::Text(
+        r#"{"type":1,"target":"vote","arguments":["x"]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let err = next_error(&mut sock).await;
+    assert_eq!(err["code"], "wrongPhase", "the connection survives");
+
+    srv.abort();
+}
@@ -0,0 +1,276 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::{
+    task,
+    time::{timeout, Duration, Instant},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use url::Url;
+use wss_server::config::ServerConfig;
+use wss_server::game::config::{GameConfig, RoleMode, ShufflePolicy};
+use wss_server::prover::mock::MockBackend;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn spawn_server() -> (u16, task::JoinHandle<()>) {
+    spawn_server_with(GameConfig::default()).await
+}
+
+async fn spawn_server_with(game: GameConfig) -> (u16, task::JoinHandle<()>) {
+    let port = portpicker::pick_unused_port().unwrap();
+    let config = ServerConfig {
+        bind: format!("127.0.0.1:{port}"),
+        game,
+        ..ServerConfig::default()
+    };
+    let server = wss_server::run_with_backend(&config, Arc::new(MockBackend::new()))
+        .await
+        .unwrap();
+    let handle = task::spawn(async move {
+        server.await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    (port, handle)
+}
+
+fn frame(target: &str, argument: Value) -> Message {
+    Message::Text(json!({"type":1,"target":target,"arguments":[argument]}).to_string())
+}
+
+/// Skip frames until one with `target` and return its payload.
+async fn wait_for(sock: &mut Socket, target: &str) -> Value {
+    loop {
+        let msg = timeout(Duration::from_secs(5), sock.next())
+            .await
+            .unwrap_or_else(|_| panic!("no {target} frame within 5s"))
+            .unwrap()
+            .unwrap();
+        if let Message::Text(txt) = msg {
+            let v: Value = serde_json::from_str(&txt).unwrap();
+            if v["target"] == target {
+                return v["arguments"][0].clone();
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn kicked_shuffler_can_join_again() {
+    let (port, srv) = spawn_server_with(GameConfig {
+        role_mode: RoleMode::MentalPoker,
+        shuffle_policy: ShufflePolicy::Kick,
+        shuffle_attempts: 1,
+        ..GameConfig::default()
+    })
+    .await;
+    let url = Url::parse(&format!("ws://127.0.0.1:{port}/ws")).unwrap();
+
+    let mut clients = Vec::new();
+    for i in 0..4 {
+        let (mut ws, _) = connect_async(url.clone()).await.unwrap();
+        ws.send(frame("join", json!({"name": format!("P{i}")})))
+            .await
+            .unwrap();
+        wait_for(&mut ws, "joined").await;
+        ws.send(frame("registerPublicKey", json!(format!("{}", i + 2))))
+            .await
+            .unwrap();
+        wait_for(&mut ws, "keyChallenge").await;
+        ws.send(frame("signChallenge", json!({"signature": "sig"})))
+            .await
+            .unwrap();
+        wait_for(&mut ws, "publicKeyRegistered").await;
+        ws.send(frame("ready", json!(true))).await.unwrap();
+        clients.push(ws);
+    }
+
+    // P0 shuffles first; one invalid proof loses the turn and the seat.
+    let turn = wait_for(&mut clients[0], "startShuffle").await;
+    clients[0]
+        .send(frame(
+            "shuffleDone",
+            json!({"encrypted_deck": turn["deck"], "proof": ""}),
+        ))
+        .await
+        .unwrap();
+    let fault = wait_for(&mut clients[1], "shuffleAborted").await;
+    assert_eq!(fault["policy"], "kick");
+    let lobby = wait_for(&mut clients[1], "lobby").await;
+    assert_eq!(lobby["players"].as_array().unwrap().len(), 3);
+
+    clients[0]
+        .send(frame("join", json!({"name": "P0"})))
+        .await
+        .unwrap();
+    let joined = wait_for(&mut clients[0], "joined").await;
+    assert!(
+        joined["playerId"].is_string(),
+        "the kicked client is free to join"
+    );
+
+    srv.abort();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn mental_poker_flow_with_mock_backend() {
+    let (port, srv) = spawn_server().await;
+    let url = Url::parse(&format!("ws://127.0.0.1:{port}/ws")).unwrap();
+
+    let mut clients = Vec::new();
+    for _ in 0..4 {
+        let (ws, _) = connect_async(url.clone()).await.unwrap();
+        clients.push(ws);
+    }
+
+    // P0 joins first and becomes host, then switches the room to mental poker.
+    clients[0]
+        .send(frame("join", json!({"name":"P0"})))
+        .await
+        .unwrap();
+    let mut room_id = String::new();
+    while room_id.is_empty() {
+        if let Some(Ok(Message::Text(txt))) = clients[0].next().await {
+            let v: Value = serde_json::from_str(&txt).unwrap();
+            if v["target"] == "joined" {
+                room_id = v["arguments"][0]["roomId"].as_str().unwrap().to_string();
+            }
+        }
+    }
+    clients[0]
+        .send(frame("configure", json!({"mode":"mentalPoker"})))
+        .await
+        .unwrap();
+
+    for (i, sock) in clients.iter_mut().enumerate().skip(1) {
+        sock.send(frame(
+            "join",
+            json!({"name":format!("P{i}"),"roomId":room_id}),
+        ))
+        .await
+        .unwrap();
+    }
+    for (i, sock) in clients.iter_mut().enumerate() {
+        sock.send(Message::Text(
+            json!({"type":1,"target":"registerPublicKey","arguments":[format!("{}", i + 2)]})
+                .to_string(),
+        ))
+        .await
+        .unwrap();
+        sock.send(Message::Text(
+            r#"{"type":1,"target":"ready","arguments":[true]}"#.into(),
+        ))
+        .await
+        .unwrap();
+    }
+
+    let mut decks: Vec<Vec<Value>> = vec![Vec::new(); 4];
+    let mut picks: Vec<Option<usize>> = vec![None; 4];
+    let mut at_night = [false; 4];
+    let mut shuffles = 0;
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline && !at_night.iter().all(|n| *n) {
+        for (idx, sock) in clients.iter_mut().enumerate() {
+            let Ok(Some(Ok(Message::Text(txt)))) =
+                timeout(Duration::from_millis(50), sock.next()).await
+            else {
+                continue;
+            };
+            let v: Value = serde_json::from_str(&txt).unwrap();
+            let args = &v["arguments"][0];
+            match v["target"].as_str().unwrap() {
+                "keyChallenge" => {
+                    assert!(args["nonce"].is_string());
+                    sock.send(frame(
+                        "signChallenge",
+                        json!({"signature": format!("sig-{idx}")}),
+                    ))
+                    .await
+                    .unwrap();
+                }
+                "startShuffle" => {
+                    shuffles += 1;
+                    sock.send(frame(
+                        "shuffleDone",
+                        json!({
+                            "encrypted_deck": args["deck"],
+                            "public_inputs": ["0"],
+                            "proof": "mock-proof"
+                        }),
+                    ))
+                    .await
+                    .unwrap();
+                }
+                "shuffleComplete" => {
+                    decks[idx] = args["deck"].as_array().unwrap().clone();
+                    sock.send(frame("pickCard", json!({"card": idx})))
+                        .await
+                        .unwrap();
+                }
+                "cardTaken" => {
+                    assert_eq!(args["status"], "ok");
+                    picks[idx] = args["card"].as_u64().map(|c| c as usize);
+                }
+                "needDecrypt" => {
+                    sock.send(frame(
+                        "decryptCard",
+                        json!({
+                            "for": args["for"],
+                            "card": args["card"],
+                            "partial": args["cipher"],
+                            "component": format!("c{idx}"),
+                            "proof": "mock-proof"
+                        }),
+                    ))
+                    .await
+                    .unwrap();
+                }
+                "allPartsReady" => {
+                    // The mock deck is never really encrypted, so the card
+                    // still carries its plaintext role message.
+                    let card = picks[idx].unwrap();
+                    let role = match decks[idx][card][1].as_str().unwrap() {
+                        "10" => "Werewolf",
+                        "1" => "Seer",
+                        _ => "Villager",
+                    };
+                    sock.send(frame(
+                        "revealRole",
+                        json!({"role": role, "proof": "mock-proof"}),
+                    ))
+                    .await
+                    .unwrap();
+                }
+                "phase" if args["phase"] == "night" => at_night[idx] = true,
+                _ => {}
+            }
+        }
+    }
+
+    assert_eq!(shuffles, 4, "every player shuffles once");
+    assert!(
+        at_night.iter().all(|n| *n),
+        "night falls once every role is revealed"
+    );
+
+    clients[0]
+        .send(frame("registerPublicKey", json!("99")))
+        .await
+        .unwrap();
+    let refused = loop {
+        let msg = timeout(Duration::from_secs(5), clients[0].next())
+            .await
+            .expect("a reply within 5s")
+            .unwrap()
+            .unwrap();
+        let v: Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+        if v["target"] == "error" {
+            break v["arguments"][0].clone();
+        }
+    };
+    assert_eq!(refused["code"], "notAllowed", "keys are fixed once dealt");
+    assert_eq!(refused["target"], "registerPublicKey");
+
+    srv.abort();
+}
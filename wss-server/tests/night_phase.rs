@@ -5,11 +5,15 @@ use tokio::{
 };
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use url::Url;
+use wss_server::config::ServerConfig;
 
 async fn spawn_server() -> (u16, task::JoinHandle<()>) {
     let port = portpicker::pick_unused_port().unwrap();
-    let bind = format!("127.0.0.1:{port}");
-    let server = wss_server::run_on(&bind).await.unwrap();
+    let config = ServerConfig {
+        bind: format!("127.0.0.1:{port}"),
+        ..ServerConfig::default()
+    };
+    let server = wss_server::run_on(&config).await.unwrap();
     let handle = task::spawn(async move {
         server.await.unwrap();
     });
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    task,
+    time::{timeout, Duration},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use wss_server::{
+    clock::ManualClock,
+    config::ServerConfig,
+    prover::mock::MockBackend,
+    room::store::{FileStore, RoomStore},
+    types::Phase,
+    ServerBuilder,
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+fn frame(target: &str, argument: Value) -> Message {
+    Message::Text(json!({"type":1,"target":target,"arguments":[argument]}).to_string())
+}
+
+async fn wait_for(sock: &mut Socket, target: &str) -> Value {
+    loop {
+        let msg = timeout(Duration::from_secs(5), sock.next())
+            .await
+            .unwrap_or_else(|_| panic!("no {target} frame within 5s"))
+            .unwrap()
+            .unwrap();
+        if let Message::Text(txt) = msg {
+            let v: Value = serde_json::from_str(&txt).unwrap();
+            if v["target"] == target {
+                return v["arguments"][0].clone();
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn builder_injects_parts_and_shuts_down_gracefully() {
+    let dir = std::env::temp_dir().join(format!("wss-builder-{}", std::process::id()));
+    let store = Arc::new(FileStore::new(&dir));
+    let config = ServerConfig {
+        bind: "127.0.0.1:0".into(),
+        shutdown_timeout: 1,
+        ..ServerConfig::default()
+    };
+    let server = ServerBuilder::new(config)
+        .backend(Arc::new(MockBackend::new()))
+        .store(store.clone())
+        .clock(Arc::new(ManualClock::new(1_000_000)))
+        .configure(|cfg| {
+            cfg.route(
+                "/health",
+                web::get().to(|| async { HttpResponse::Ok().body("ok") }),
+            );
+        })
+        .build()
+        .unwrap();
+    let addr = server.local_addrs()[0];
+    let shutdown = server.shutdown_handle();
+    let srv = task::spawn(async move { server.await });
+
+    let health = task::spawn_blocking(move || {
+        ureq::get(&format!("http://{addr}/health"))
+            .call()
+            .unwrap()
+            .into_string()
+            .unwrap()
+    })
+    .await
+    .unwrap();
+    assert_eq!(health, "ok");
+
+    let mut players = Vec::new();
+    for i in 0..4 {
+        let (mut ws, _) = connect_async(format!("ws://{addr}/ws")).await.unwrap();
+        ws.send(frame("join", json!({"name": format!("P{i}")})))
+            .await
+            .unwrap();
+        wait_for(&mut ws, "joined").await;
+        ws.send(frame("ready", json!(true))).await.unwrap();
+        players.push(ws);
+    }
+    let phase = wait_for(&mut players[0], "phase").await;
+    assert_eq!(phase["phase"], "night");
+    assert_eq!(
+        phase["deadline"], 1_030_000,
+        "deadlines follow the injected clock"
+    );
+
+    shutdown.shutdown().await;
+    timeout(Duration::from_secs(5), srv)
+        .await
+        .expect("the server stops")
+        .unwrap()
+        .unwrap();
+    assert!(connect_async(format!("ws://{addr}/ws")).await.is_err());
+
+    let saved = store.load_all().unwrap();
+    assert_eq!(saved.len(), 1, "the room in play is saved on shutdown");
+    assert_eq!(saved[0].phase, Phase::Night);
+    std::fs::remove_dir_all(dir).unwrap();
+}
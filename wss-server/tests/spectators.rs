@@ -0,0 +1,103 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    task,
+    time::{timeout, Duration},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use url::Url;
+use wss_server::config::ServerConfig;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn spawn_server() -> (u16, task::JoinHandle<()>) {
+    let port = portpicker::pick_unused_port().unwrap();
+    let config = ServerConfig {
+        bind: format!("127.0.0.1:{port}"),
+        ..ServerConfig::default()
+    };
+    let server = wss_server::run_on(&config).await.unwrap();
+    let handle = task::spawn(async move {
+        server.await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    (port, handle)
+}
+
+fn frame(target: &str, argument: Value) -> Message {
+    Message::Text(json!({"type":1,"target":target,"arguments":[argument]}).to_string())
+}
+
+/// Skip frames until one with `target` arrives; return every target seen
+/// on the way, including it, and its payload.
+async fn wait_for(sock: &mut Socket, target: &str) -> (Vec<String>, Value) {
+    let mut seen = Vec::new();
+    loop {
+        let msg = timeout(Duration::from_secs(5), sock.next())
+            .await
+            .unwrap_or_else(|_| panic!("no {target} frame within 5s, saw {seen:?}"))
+            .unwrap()
+            .unwrap();
+        if let Message::Text(txt) = msg {
+            let v: Value = serde_json::from_str(&txt).unwrap();
+            seen.push(v["target"].as_str().unwrap().to_string());
+            if v["target"] == target {
+                return (seen, v["arguments"][0].clone());
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn spectator_sees_public_frames_only() {
+    let (port, srv) = spawn_server().await;
+    let url = Url::parse(&format!("ws://127.0.0.1:{port}/ws")).unwrap();
+
+    let mut players = Vec::new();
+    for _ in 0..4 {
+        let (ws, _) = connect_async(url.clone()).await.unwrap();
+        players.push(ws);
+    }
+    players[0]
+        .send(frame("join", json!({"name":"P0"})))
+        .await
+        .unwrap();
+    let (_, joined) = wait_for(&mut players[0], "joined").await;
+    let room_id = joined["roomId"].as_str().unwrap().to_string();
+
+    let (mut watcher, _) = connect_async(url.clone()).await.unwrap();
+    watcher
+        .send(frame("spectate", json!({"roomId": room_id})))
+        .await
+        .unwrap();
+    let (_, state) = wait_for(&mut watcher, "spectating").await;
+    assert_eq!(state["phase"], "lobby");
+    assert_eq!(state["players"].as_array().unwrap().len(), 1);
+    assert!(state["delay"].is_null());
+
+    watcher.send(frame("vote", json!("x"))).await.unwrap();
+    let (_, err) = wait_for(&mut watcher, "error").await;
+    assert_eq!(err["code"], "notAllowed");
+
+    for (i, sock) in players.iter_mut().enumerate().skip(1) {
+        sock.send(frame(
+            "join",
+            json!({"name":format!("P{i}"),"roomId":room_id}),
+        ))
+        .await
+        .unwrap();
+    }
+    for sock in players.iter_mut() {
+        sock.send(frame("ready", json!(true))).await.unwrap();
+    }
+
+    let (seen, phase) = wait_for(&mut watcher, "phase").await;
+    assert_eq!(phase["phase"], "night");
+    assert!(seen.contains(&"gameStart".to_string()));
+    assert!(
+        !seen.contains(&"role".to_string()),
+        "roles are private: {seen:?}"
+    );
+
+    srv.abort();
+}
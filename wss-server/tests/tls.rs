@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::{
+    net::TcpStream,
+    task,
+    time::{timeout, Duration},
+};
+use tokio_rustls::{
+    rustls::{
+        self,
+        pki_types::{pem::PemObject, CertificateDer, ServerName},
+    },
+    TlsConnector,
+};
+use tokio_tungstenite::{client_async, tungstenite::Message};
+use wss_server::{
+    config::{ServerConfig, TlsConfig},
+    ServerBuilder,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// A client that trusts only the test certificate.
+fn connector() -> TlsConnector {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(fixture("localhost.crt")).unwrap() {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn websocket_over_tls() {
+    let config = ServerConfig {
+        bind: "127.0.0.1:0".into(),
+        tls: Some(TlsConfig {
+            cert: fixture("localhost.crt"),
+            key: fixture("localhost.key"),
+            reload_interval: None,
+        }),
+        ..ServerConfig::default()
+    };
+    let server = ServerBuilder::new(config).build().unwrap();
+    let addr = server.local_addrs()[0];
+    let srv = task::spawn(async move { server.await });
+
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let tls = connector()
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await
+        .unwrap();
+    let (mut ws, _) = client_async("wss://localhost/ws", tls).await.unwrap();
+    ws.send(Message::Text(
+        r#"{"type":1,"target":"listRooms","arguments":[]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let msg = timeout(Duration::from_secs(5), ws.next())
+        .await
+        .expect("a reply within 5s")
+        .unwrap()
+        .unwrap();
+    let v: Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+    assert_eq!(v["target"], "rooms");
+
+    let plain = TcpStream::connect(addr).await.unwrap();
+    assert!(
+        client_async(format!("ws://{addr}/ws"), plain)
+            .await
+            .is_err(),
+        "plain WebSocket is not served on a TLS listener"
+    );
+
+    srv.abort();
+}